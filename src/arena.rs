@@ -0,0 +1,251 @@
+use std::marker::PhantomData;
+
+use rand::Rng;
+
+/// Typed index into an [`Arena`].
+///
+/// Besides the slot, a handle records the generation of the element it was
+/// created for. Slots are reused after removal, so the generation tells a
+/// handle to a removed element apart from one to the element now occupying
+/// its slot.
+pub trait Handle: Copy {
+    fn new(index: usize, generation: u32) -> Self;
+    fn index(self) -> usize;
+    fn generation(self) -> u32;
+}
+
+/// Declares a [`Handle`] type with the given name, displayed as its index.
+macro_rules! handle {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name {
+            index: usize,
+            generation: u32,
+        }
+
+        impl $crate::arena::Handle for $name {
+            fn new(index: usize, generation: u32) -> Self {
+                $name { index, generation }
+            }
+
+            fn index(self) -> usize {
+                self.index
+            }
+
+            fn generation(self) -> u32 {
+                self.generation
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.index)
+            }
+        }
+    };
+}
+
+pub(crate) use handle;
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    /// Incremented whenever the element in the slot is removed
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Contiguous storage of mesh elements addressed by typed handles.
+///
+/// Removed slots are kept on a free list and reused by later insertions, so
+/// removal is O(1). Handles of removed elements stay invalid after their
+/// slot is reused: looking them up returns `None`, and indexing with them
+/// panics.
+#[derive(Debug, Clone)]
+pub struct Arena<H, T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    len: usize,
+    handle: PhantomData<H>,
+}
+
+impl<H, T> Default for Arena<H, T> {
+    fn default() -> Self {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            handle: PhantomData,
+        }
+    }
+}
+
+impl<H: Handle, T> Arena<H, T> {
+    pub fn insert(&mut self, value: T) -> H {
+        self.len += 1;
+
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                H::new(index, slot.generation)
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                H::new(self.slots.len() - 1, 0)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: H) -> Option<T> {
+        let slot = self.slot_mut(handle)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+
+        self.free.push(handle.index());
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn get(&self, handle: H) -> Option<&T> {
+        self.slots
+            .get(handle.index())
+            .filter(|slot| slot.generation == handle.generation())?
+            .value
+            .as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: H) -> Option<&mut T> {
+        self.slot_mut(handle)?.value.as_mut()
+    }
+
+    fn slot_mut(&mut self, handle: H) -> Option<&mut Slot<T>> {
+        self.slots
+            .get_mut(handle.index())
+            .filter(|slot| slot.generation == handle.generation())
+    }
+
+    pub fn contains(&self, handle: H) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Number of live elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Upper bound of the handle indices, including free slots
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (H, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            Some((H::new(index, slot.generation), slot.value.as_ref()?))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (H, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                Some((H::new(index, slot.generation), slot.value.as_mut()?))
+            })
+    }
+
+    /// A live element picked uniformly at random, `None` if the arena is
+    /// empty. Takes a few tries on average unless most slots are free.
+    pub fn random_id<R: Rng>(&self, rng: &mut R) -> Option<H> {
+        if self.len == 0 {
+            return None;
+        }
+
+        for _ in 0..16 {
+            let index = rng.gen_range(0..self.slots.len());
+            let slot = &self.slots[index];
+            if slot.value.is_some() {
+                return Some(H::new(index, slot.generation));
+            }
+        }
+
+        self.ids().nth(rng.gen_range(0..self.len))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = H> + '_ {
+        self.iter().map(|(handle, _)| handle)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
+
+impl<H: Handle, T> std::ops::Index<H> for Arena<H, T> {
+    type Output = T;
+
+    fn index(&self, handle: H) -> &T {
+        self.get(handle)
+            .expect("Handle refers to a removed element")
+    }
+}
+
+impl<H: Handle, T> std::ops::IndexMut<H> for Arena<H, T> {
+    fn index_mut(&mut self, handle: H) -> &mut T {
+        self.get_mut(handle)
+            .expect("Handle refers to a removed element")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::vertex::VertexId;
+
+    use super::*;
+
+    #[test]
+    fn stale_handles_do_not_alias_reused_slots() {
+        let mut arena = Arena::<VertexId, &str>::default();
+
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        assert_eq!(arena.remove(a), Some("a"));
+
+        let c = arena.insert("c");
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+
+        assert_eq!(arena.get(a), None);
+        assert!(!arena.contains(a));
+        assert_eq!(arena.remove(a), None);
+        assert_eq!(arena[c], "c");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.ids().collect::<Vec<_>>(), vec![c, b]);
+    }
+
+    #[test]
+    fn random_ids_are_live() {
+        let mut arena = Arena::<VertexId, usize>::default();
+        let ids = (0..100).map(|i| arena.insert(i)).collect::<Vec<_>>();
+        for &id in ids.iter().skip(1) {
+            arena.remove(id);
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10 {
+            assert_eq!(arena.random_id(&mut rng), Some(ids[0]));
+        }
+
+        arena.remove(ids[0]);
+        assert_eq!(arena.random_id(&mut rng), None);
+    }
+}
@@ -1,509 +1,1051 @@
 use std::{
-    cell::RefCell,
-    collections::{HashMap, VecDeque},
-    rc::Rc,
+    cell::Cell,
+    collections::{HashMap, HashSet, VecDeque},
 };
 
 use glam::DVec2;
 
 use crate::{
+    arena::Arena,
     constraints::constraints::{ConstraintSegment, Constraints},
-    edge::Edge,
-    face::{Face, ToIndices},
+    edge::{Edge, EdgeId},
+    error::{CadetError, CadetResult},
+    face::{Face, FaceId},
     helper::{intersection_point, is_ccw, is_crossing, ProjectToLine},
     locate_result::LocateResult,
     orientation::Orientation,
-    sym_edge::SymEdge,
+    predicates::{in_circumcircle, orient2d},
+    projection::Projection,
+    sym_edge::{SymEdge, SymEdgeId},
     symmetric_compare::{Flipped, SymmetricCompare},
-    vertex::Vertex,
+    vertex::{Vertex, VertexId},
 };
 
-#[derive(Debug, Default)]
+/// Distance, relative to the length of the crossing edges, within which a
+/// rounded intersection is merged into an end point of either edge
+const SNAP_TOLERANCE: f64 = 1e-12;
+
+/// Element of the triangulation met while walking along a segment
+#[derive(Debug, PartialEq)]
+enum SegmentCrossing {
+    Vertex(VertexId),
+    Edge(EdgeId),
+}
+
+/// Constrained edge split where a segment crosses it
+struct SplitConstraint {
+    /// Constraints of the split edge
+    crep: Vec<usize>,
+    /// Intersections met while routing them through the crossing
+    rerouted: Vec<ConstraintIntersection>,
+}
+
+impl SplitConstraint {
+    /// Ids of the constraints passing through the crossing, in ascending order
+    fn constraints(&self, constraint_id: usize) -> Vec<usize> {
+        let mut constraints = self.crep.clone();
+        constraints.push(constraint_id);
+        constraints.sort_unstable();
+        constraints.dedup();
+        constraints
+    }
+}
+
+/// A constraint crossing an already inserted one.
+///
+/// The crossed constrained edge is split at a Steiner vertex shared by both
+/// constraints, and its two halves keep every constraint it represented.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintIntersection {
+    /// The Steiner vertex inserted at the crossing
+    pub vertex: VertexId,
+    pub position: DVec2,
+    /// Ids of the constraints passing through the vertex, in ascending order
+    pub constraints: Vec<usize>,
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct CDT {
-    pub vertices: Vec<Rc<RefCell<Vertex>>>,
-    pub edges: Vec<Rc<RefCell<Edge>>>,
-    pub faces: Vec<Rc<RefCell<Face>>>,
-    pub sym_edges_by_half_edges: HashMap<(usize, usize), Rc<RefCell<SymEdge>>>,
-    pub sym_edges_by_vertices: HashMap<usize, Vec<Rc<RefCell<SymEdge>>>>,
-    pub constraints: HashMap<usize, Vec<usize>>,
-    pub face_id_counter: usize,
+    pub vertices: Arena<VertexId, Vertex>,
+    pub edges: Arena<EdgeId, Edge>,
+    pub faces: Arena<FaceId, Face>,
+    pub sym_edges: Arena<SymEdgeId, SymEdge>,
+    pub edges_by_vertices: HashMap<(VertexId, VertexId), EdgeId>,
+    pub sym_edges_by_half_edges: HashMap<(VertexId, VertexId), SymEdgeId>,
+    pub sym_edges_by_vertices: HashMap<VertexId, Vec<SymEdgeId>>,
+    /// Vertices referencing each inserted constraint
+    pub constraints: HashMap<usize, Vec<VertexId>>,
+    /// Plane the model was projected onto, inverted when exporting
+    pub projection: Projection,
+    /// Face found by the last point location, used to start the next walk
+    pub(crate) last_located_face: Cell<Option<FaceId>>,
 }
 
 impl CDT {
-    pub fn add_constraints(&mut self, constraints: &Constraints) {
+    /// Inserts every constraint, returning the intersections between them
+    pub fn add_constraints(
+        &mut self,
+        constraints: &Constraints,
+    ) -> CadetResult<Vec<ConstraintIntersection>> {
+        let mut intersections = Vec::new();
+
         for constraint_segment in constraints.constraint_segments.iter() {
-            self.insert_constraint(constraint_segment);
+            intersections.extend(self.insert_constraint(constraint_segment)?);
         }
+
+        Ok(intersections)
     }
 
-    pub fn insert_constraint(&mut self, constraint_segment: &ConstraintSegment) {
-        let vertex_list = constraint_segment
+    /// Inserts a constraint, returning where it crosses the constraints that
+    /// are already in the triangulation. Closed constraints also get the edge
+    /// from their last point back to the first
+    pub fn insert_constraint(
+        &mut self,
+        constraint_segment: &ConstraintSegment,
+    ) -> CadetResult<Vec<ConstraintIntersection>> {
+        let id = constraint_segment.id;
+
+        if constraint_segment.constraints.is_empty() {
+            return Err(CadetError::EmptyConstraint(id));
+        }
+
+        // Check every point before the triangulation is modified
+        let outside_point = constraint_segment
             .constraints
             .iter()
-            .filter_map(|point| {
+            .find(|point| matches!(self.locate_point(point), LocateResult::None));
+
+        if let Some(&point) = outside_point {
+            return Err(CadetError::OutsideDomain {
+                constraint: id,
+                point,
+            });
+        }
+
+        let mut vertex_list = constraint_segment
+            .constraints
+            .iter()
+            .map(|point| {
                 // Step 1: Locate the point in the triangulation
-                let locate_result = self.locate_point(point);
+                let locate_result = self.snap_to_vertex(point, self.locate_point(point));
 
                 // Step 2: Handle the locate result
                 match locate_result {
-                    LocateResult::Vertex(v) => Some(v),
-                    LocateResult::Edge(edge) => Some(self.insert_point_on_edge(*point, edge)),
-                    LocateResult::Face(face) => Some(self.insert_point_in_face(*point, face)),
-                    LocateResult::None => None,
+                    LocateResult::Vertex(v) => Ok(v),
+                    LocateResult::Edge(edge) => Ok(self.insert_point_on_edge(*point, edge)),
+                    LocateResult::Face(face) => Ok(self.insert_point_in_face(*point, face)),
+                    LocateResult::None => Err(CadetError::OutsideDomain {
+                        constraint: id,
+                        point: *point,
+                    }),
                 }
             })
-            .collect::<Vec<_>>();
+            .collect::<CadetResult<Vec<_>>>()?;
+
+        // Points snapped to the same vertex would form empty segments
+        vertex_list.dedup();
+
+        // A loop of two vertices would only insert its segment twice
+        let first = vertex_list[0];
+        if constraint_segment.closed && vertex_list.len() > 2 && vertex_list.last() != Some(&first)
+        {
+            vertex_list.push(first);
+        }
 
-        // self.export_to_obj("./models/output.obj");
+        // Loops end at their first vertex, only count it once
+        let mut referenced = HashSet::new();
+        for &vertex in vertex_list.iter() {
+            if referenced.insert(vertex) {
+                self.vertices[vertex].constraints += 1;
+                self.constraints.entry(id).or_default().push(vertex);
+            }
+        }
 
-        // // Wait 100ms
-        // std::thread::sleep(std::time::Duration::from_millis(1000));
+        // Step 3: Insert segments between successive vertices
+        let mut intersections = Vec::new();
 
-        // // Step 3: Insert segments between successive vertices
-        for i in 0..vertex_list.len() - 1 {
-            let v = vertex_list[i].clone();
-            let vs = vertex_list[i + 1].clone();
-            self.insert_segment(v, vs, constraint_segment.id);
+        for pair in vertex_list.windows(2) {
+            intersections.extend(self.insert_segment(pair[0], pair[1], id)?);
         }
+
+        Ok(intersections)
     }
 
-    fn insert_point_on_edge(
-        &mut self,
-        point: DVec2,
-        edge: Rc<RefCell<Edge>>,
-    ) -> Rc<RefCell<Vertex>> {
-        let edge = edge.borrow();
-
-        let position = point.project_to_line(&(edge.a.borrow().position, edge.b.borrow().position));
-        let v = self.add_vertex(position, 1);
-        let edge_indices = edge.edge_indices();
-
-        let sym_edge = self.get_sym_edge_for_half_edge(&edge_indices).unwrap();
-        let face_1 = sym_edge.borrow().face.clone();
-        let face_2 = sym_edge.borrow().neighbor_face();
-
-        let face_2 = match face_2 {
-            Some(face) => face,
-            None => {
-                return v;
+    /// Turns the location of a point a rounding error away from a vertex of
+    /// the edge or face it lies in into the location of that vertex
+    fn snap_to_vertex(&self, point: &DVec2, locate_result: LocateResult) -> LocateResult {
+        let vertices = match locate_result {
+            LocateResult::Edge(edge) => {
+                let (a, b) = self.edges[edge].edge_indices();
+                vec![a, b]
             }
+            LocateResult::Face(face) => self.faces[face].vertices.to_vec(),
+            LocateResult::Vertex(_) | LocateResult::None => return locate_result,
         };
 
-        assert!(face_1.as_ptr() != face_2.as_ptr());
+        let positions = vertices
+            .iter()
+            .map(|&v| self.vertices[v].position)
+            .collect::<Vec<_>>();
 
-        // Remove the old faces
-        self.remove_face(face_1.clone());
-        self.remove_face(face_2.clone());
+        let size = positions
+            .iter()
+            .flat_map(|p| positions.iter().map(move |q| p.distance(*q)))
+            .fold(0., f64::max);
+
+        vertices
+            .into_iter()
+            .zip(positions)
+            .find(|(_, position)| position.distance(*point) <= SNAP_TOLERANCE * size)
+            .map_or(locate_result, |(v, _)| LocateResult::Vertex(v))
+    }
 
-        // Get the edges that are diffferent from e
-        let face_1_edges = face_1.borrow().edges();
-        let face_2_edges = face_2.borrow().edges();
-
-        let filter_and_map_edges = |edges: &[(Rc<RefCell<Vertex>>, Rc<RefCell<Vertex>>)]| {
-            edges
-                .iter()
-                .filter(|face_edge| {
-                    !(face_edge.0.borrow().index, face_edge.1.borrow().index)
-                        .symmetric_compare(&edge_indices)
-                })
-                .map(|edge| (edge.0.clone(), edge.1.clone()))
-                .collect::<Vec<_>>()
+    pub fn insert_point_on_edge(&mut self, point: DVec2, edge: EdgeId) -> VertexId {
+        let (a, b) = self.edges[edge].edge_indices();
+        let a = self.vertices[a].position;
+        let b = self.vertices[b].position;
+
+        // Projecting a point that is exactly on the edge could only move it off
+        let position = if orient2d(a, b, point) == 0. {
+            point
+        } else {
+            point.project_to_line(&(a, b))
         };
+        let height = self.edge_height(edge, position);
+        let v = self.add_vertex(position, 0);
+        self.vertices[v].height = height;
+        self.connect_vertex_on_edge(v, edge);
+
+        v
+    }
+
+    /// Height at `p` interpolated linearly along the edge
+    pub(crate) fn edge_height(&self, edge: EdgeId, p: DVec2) -> f64 {
+        let a = &self.vertices[self.edges[edge].a];
+        let b = &self.vertices[self.edges[edge].b];
+
+        let direction = b.position - a.position;
+        let t = ((p - a.position).dot(direction) / direction.length_squared()).clamp(0., 1.);
+
+        a.height + t * (b.height - a.height)
+    }
+
+    /// Splits `edge` at the unconnected vertex `v`, which lies on the edge
+    pub(crate) fn connect_vertex_on_edge(&mut self, v: VertexId, edge: EdgeId) {
+        let edge_indices = self.edges[edge].edge_indices();
+
+        let sym_edge = self
+            .get_sym_edge_for_half_edge(&edge_indices)
+            .or_else(|| self.get_sym_edge_for_half_edge(&edge_indices.flipped()))
+            .unwrap();
+        let face_1 = self.sym_edges[sym_edge].face;
+        let face_2 = self.neighbor_face(sym_edge);
+
+        if let Some(face_2) = face_2 {
+            assert!(face_1 != face_2);
+        }
+
+        // Both halves of the split edge represent its constraints
+        let crep = self.edges[edge].crep.clone();
+        for &constraint in crep.iter() {
+            self.reference_vertex(v, constraint);
+        }
+
+        // Remove the old faces, a boundary edge only has a single one
+        let old_face_ids = [Some(face_1), face_2]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
 
-        let face_1_edges = filter_and_map_edges(&face_1_edges);
-        let face_2_edges = filter_and_map_edges(&face_2_edges);
+        let old_faces = old_face_ids
+            .iter()
+            .map(|&face| self.faces[face].clone())
+            .collect::<Vec<_>>();
 
-        assert!(face_1_edges.len() == 2);
-        assert!(face_2_edges.len() == 2);
+        let removed_constraints = self.face_edge_constraints(&old_face_ids);
+
+        for &face in old_face_ids.iter() {
+            self.remove_face(face);
+        }
 
-        let new_faces = face_1_edges
+        // Get the edges that are diffferent from e
+        let outer_edges = old_faces
             .iter()
-            .chain(face_2_edges.iter())
-            .map(|(a, b)| self.add_face([a.clone(), b.clone(), v.clone()]))
+            .flat_map(|face| {
+                let face_edges = face
+                    .edge_indices()
+                    .into_iter()
+                    .filter(|face_edge| !face_edge.symmetric_compare(&edge_indices))
+                    .collect::<Vec<_>>();
+                assert!(face_edges.len() == 2);
+                face_edges
+            })
             .collect::<Vec<_>>();
 
+        let new_faces = outer_edges
+            .iter()
+            .map(|&(a, b)| self.add_face([a, b, v]))
+            .collect::<Vec<_>>();
+
+        self.restore_edge_constraints(removed_constraints);
+
+        for end in [edge_indices.0, edge_indices.1] {
+            let half = self.find_edge(end, v).unwrap();
+            self.edges[half].crep.extend(crep.iter().copied());
+        }
+
         let mut edges = new_faces
             .iter()
-            .flat_map(|face| face.borrow().edges())
+            .flat_map(|&face| self.faces[face].edge_indices())
             .filter(|edge| {
-                [face_1_edges.clone(), face_2_edges.clone()]
+                outer_edges
                     .iter()
-                    .flatten()
                     .any(|face_edge| face_edge.symmetric_compare(edge))
             })
-            .map(|edge| self.get_sym_edge_for_half_edge(&edge.to_indices()).unwrap())
-            .map(|edge| edge.borrow().edge.clone())
+            .map(|edge| self.find_edge(edge.0, edge.1).unwrap())
             .collect::<Vec<_>>();
 
         edges.reverse();
 
-        assert!(edges.len() == 4);
+        assert!(edges.len() == 2 * old_faces.len());
 
         let mut edge_stack = VecDeque::new();
-        edge_stack.extend(edges.clone());
+        edge_stack.extend(edges);
 
-        // self.export_to_obj("./models/output.obj");
+        self.flip_edges(v, &mut edge_stack);
+        self.start_walks_at(v);
+    }
 
-        //Waiting for user input
-        // let mut input = String::new();
-        // std::io::stdin().read_line(&mut input).unwrap();
+    pub fn insert_point_in_face(&mut self, v: DVec2, face: FaceId) -> VertexId {
+        let height = self.face_height(face, v);
 
-        self.flip_edges(v.clone(), &mut edge_stack);
+        //New vertex
+        let v = self.add_vertex(v, 0);
+        self.vertices[v].height = height;
+        self.connect_vertex_in_face(v, face);
 
         v
     }
 
-    pub fn insert_point_in_face(
-        &mut self,
-        v: DVec2,
-        face: Rc<RefCell<Face>>,
-    ) -> Rc<RefCell<Vertex>> {
-        //New vertex
-        let v = self.add_vertex(v, 1);
-        self.remove_face(face.clone());
+    /// Height at `p` interpolated with the barycentric coordinates of the face
+    pub(crate) fn face_height(&self, face: FaceId, p: DVec2) -> f64 {
+        let [a, b, c] = self.faces[face].vertices.map(|v| &self.vertices[v]);
 
-        let face_borrowed = face.borrow();
+        let area = orient2d(a.position, b.position, c.position);
+        let wa = orient2d(p, b.position, c.position) / area;
+        let wb = orient2d(a.position, p, c.position) / area;
+        let wc = 1. - wa - wb;
 
-        let new_faces = face_borrowed
-            .edges()
+        wa * a.height + wb * b.height + wc * c.height
+    }
+
+    /// Splits `face` into three faces around the unconnected vertex `v`,
+    /// which lies inside it
+    pub(crate) fn connect_vertex_in_face(&mut self, v: VertexId, face: FaceId) {
+        let old_face = self.faces[face].clone();
+        let removed_constraints = self.face_edge_constraints(&[face]);
+        self.remove_face(face);
+
+        let new_faces = old_face
+            .edge_indices()
             .iter()
-            .map(|edge| {
-                let vertices = [edge.0.clone(), edge.1.clone(), v.clone()];
-                self.add_face(vertices)
-            })
+            .map(|edge| self.add_face([edge.0, edge.1, v]))
             .collect::<Vec<_>>();
 
+        self.restore_edge_constraints(removed_constraints);
+
         let edges = new_faces
             .iter()
-            .flat_map(|face| face.borrow().edges())
+            .flat_map(|&face| self.faces[face].edge_indices())
             .filter(|edge| {
-                face_borrowed
-                    .edges()
+                old_face
+                    .edge_indices()
                     .iter()
                     .any(|face_edge| face_edge.symmetric_compare(edge))
             })
-            .map(|edge| self.get_sym_edge_for_half_edge(&edge.to_indices()).unwrap())
-            .map(|edge| edge.borrow().edge.clone())
+            .map(|edge| self.find_edge(edge.0, edge.1).unwrap())
             .collect::<Vec<_>>();
 
         assert!(edges.len() == 3);
 
         let mut edge_stack = VecDeque::new();
-        edge_stack.extend(edges.clone());
+        edge_stack.extend(edges);
+
+        self.flip_edges(v, &mut edge_stack);
+        self.start_walks_at(v);
+    }
+
+    /// Inserts the segment from `start` to `end` as an edge of constraint
+    /// `constraint_id`, returning where it crosses other constraints.
+    ///
+    /// The segment is walked from `start`. Vertices met on it split it into
+    /// pieces that are inserted one at a time, and every constrained edge it
+    /// crosses is split at a Steiner vertex. The rounded Steiner vertex is
+    /// generally not on the segment, so the rest of the segment is walked
+    /// again from the actual vertex instead of following the original line.
+    pub(crate) fn insert_segment(
+        &mut self,
+        start: VertexId,
+        end: VertexId,
+        constraint_id: usize,
+    ) -> CadetResult<Vec<ConstraintIntersection>> {
+        // Vertices the segment still has to pass through, the next on top
+        let mut targets = vec![end];
+        let mut start = start;
+        let mut intersections = Vec::new();
+
+        while let Some(&target) = targets.last() {
+            self.reference_vertex(start, constraint_id);
+
+            if let Some(edge) = self.find_edge(start, target) {
+                self.edges[edge].insert_constraint(constraint_id);
+                start = target;
+                targets.pop();
+                continue;
+            }
 
-        // self.export_to_obj("./models/output.obj");
+            // The segment is inserted up to the first vertex or constrained
+            // edge it meets
+            let mut crossed_edges = Vec::new();
+            let mut obstacle = None;
 
-        //Waiting for user input
-        // let mut input = String::new();
-        // std::io::stdin().read_line(&mut input).unwrap();
+            for crossing in self.segment_crossings(start, target).into_iter().skip(1) {
+                match crossing {
+                    SegmentCrossing::Edge(edge) if !self.edges[edge].is_constrained() => {
+                        crossed_edges.push(edge);
+                    }
+                    crossing => {
+                        obstacle = Some(crossing);
+                        break;
+                    }
+                }
+            }
 
-        self.flip_edges(v.clone(), &mut edge_stack);
+            match obstacle {
+                Some(SegmentCrossing::Vertex(vertex)) => {
+                    match self.find_edge(start, vertex) {
+                        Some(edge) => self.edges[edge].insert_constraint(constraint_id),
+                        None => self.insert_cavity_segment(
+                            start,
+                            vertex,
+                            &crossed_edges,
+                            constraint_id,
+                        )?,
+                    }
 
-        v
+                    start = vertex;
+                    if vertex == target {
+                        targets.pop();
+                    }
+                }
+                Some(SegmentCrossing::Edge(edge)) => {
+                    let (vertex, crossing) = self.split_constraint(start, target, edge)?;
+
+                    if let Some(crossing) = crossing {
+                        let constraints = crossing.constraints(constraint_id);
+                        intersections.extend(crossing.rerouted);
+                        intersections.push(ConstraintIntersection {
+                            vertex,
+                            position: self.vertices[vertex].position,
+                            constraints,
+                        });
+                    }
+
+                    if vertex != start && vertex != target {
+                        targets.push(vertex);
+                    }
+                }
+                None => {
+                    return Err(CadetError::TopologyCorrupt(format!(
+                        "Segment ({}, {}) reaches neither its end nor a constrained edge",
+                        start, target
+                    )))
+                }
+            }
+        }
+
+        self.reference_vertex(start, constraint_id);
+
+        Ok(intersections)
+    }
+
+    /// Splits the constrained `edge` where the segment from `start` to `end`
+    /// crosses it, and returns the vertex at the crossing.
+    ///
+    /// The rounded intersection is merged into a nearby end point of either
+    /// edge. Unless it is merged into an end point of `edge`, the constraints
+    /// of `edge` are routed through the vertex, and the crossing is returned
+    /// along with the intersections found while rerouting them.
+    fn split_constraint(
+        &mut self,
+        start: VertexId,
+        end: VertexId,
+        edge: EdgeId,
+    ) -> CadetResult<(VertexId, Option<SplitConstraint>)> {
+        let (c, d) = self.edges[edge].edge_indices();
+
+        let a = self.vertices[start].position;
+        let b = self.vertices[end].position;
+        let segment = (self.vertices[c].position, self.vertices[d].position);
+
+        let intersection_point = intersection_point(&(a, b), &segment).ok_or_else(|| {
+            CadetError::TopologyCorrupt(format!(
+                "Constrained edge {} does not intersect the segment",
+                self.edges[edge]
+            ))
+        })?;
+
+        // The rounded intersection is generally not exactly on the edge, so it
+        // is located like any other point
+        let position = intersection_point.project_to_line(&segment);
+        let crep = self.edges[edge].crep.iter().copied().collect::<Vec<_>>();
+
+        // Vertices a rounding error apart would have no usable angles between
+        // their edges
+        let tolerance = SNAP_TOLERANCE * a.distance(b).max(segment.0.distance(segment.1));
+        let snapped = [c, d, start, end]
+            .into_iter()
+            .find(|&v| self.vertices[v].position.distance(position) <= tolerance);
+
+        let vertex =
+            match snapped.map_or_else(|| self.locate_point(&position), LocateResult::Vertex) {
+                LocateResult::Vertex(vertex) => vertex,
+                LocateResult::Edge(split) => {
+                    let vertex = self.insert_point_on_edge(position, split);
+                    self.vertices[vertex].steiner = true;
+                    vertex
+                }
+                LocateResult::Face(face) => {
+                    let vertex = self.insert_point_in_face(position, face);
+                    self.vertices[vertex].steiner = true;
+                    vertex
+                }
+                LocateResult::None => return Err(CadetError::PointOutsideDomain(position)),
+            };
+
+        if vertex == c || vertex == d {
+            return Ok((vertex, None));
+        }
+
+        // Unless the edge was split at the vertex, its constraints are routed
+        // through it
+        let rerouted = match self.find_edge(c, d) {
+            Some(edge) => self.reroute_constraints(edge, vertex)?,
+            None => Vec::new(),
+        };
+
+        Ok((vertex, Some(SplitConstraint { crep, rerouted })))
+    }
+
+    /// Routes the constraints of `edge` through `vertex`, which lies on the
+    /// edge up to rounding, replacing the edge by two constrained segments.
+    fn reroute_constraints(
+        &mut self,
+        edge: EdgeId,
+        vertex: VertexId,
+    ) -> CadetResult<Vec<ConstraintIntersection>> {
+        let (c, d) = self.edges[edge].edge_indices();
+        let crep = std::mem::take(&mut self.edges[edge].crep);
+
+        let mut constraints = crep.into_iter().collect::<Vec<_>>();
+        constraints.sort_unstable();
+
+        let mut intersections = Vec::new();
+
+        for constraint in constraints {
+            intersections.extend(self.insert_segment(c, vertex, constraint)?);
+            intersections.extend(self.insert_segment(vertex, d, constraint)?);
+        }
+
+        // The vertex was inserted while the edge could not be flipped
+        if let Some(edge) = self.find_edge(c, d) {
+            self.legalize_edges(VecDeque::from([edge]));
+        }
+
+        Ok(intersections)
     }
 
-    fn insert_segment(
+    /// Records that the constraint passes through the vertex, once
+    fn reference_vertex(&mut self, vertex: VertexId, constraint_id: usize) {
+        let vertices = self.constraints.entry(constraint_id).or_default();
+
+        if !vertices.contains(&vertex) {
+            vertices.push(vertex);
+            self.vertices[vertex].constraints += 1;
+        }
+    }
+
+    /// Inserts the edge from `start` to `end`, which crosses only the
+    /// unconstrained `crossed_edges`, by retriangulating the faces around
+    /// them on both sides of the new edge.
+    fn insert_cavity_segment(
         &mut self,
-        start: Rc<RefCell<Vertex>>,
-        end: Rc<RefCell<Vertex>>,
+        start: VertexId,
+        end: VertexId,
+        crossed_edges: &[EdgeId],
         constraint_id: usize,
-    ) {
-        let edge_list = self.find_crossing_edges(start.clone(), end.clone());
+    ) -> CadetResult<()> {
+        let mut cavity = Vec::new();
+
+        for &edge in crossed_edges {
+            for sym_edge in self.get_all_sym_edges_for_edge(edge) {
+                let face = self.sym_edges[sym_edge].face;
+                if !cavity.contains(&face) {
+                    cavity.push(face);
+                }
+            }
+        }
 
-        let constrained_edges = edge_list
-            .iter()
-            .filter(|edge| edge.borrow().is_constrained());
+        let (top_vertices, bottom_vertices) = self
+            .cavity_chains(&cavity, start, end, crossed_edges)
+            .ok_or_else(|| {
+                CadetError::TopologyCorrupt(format!(
+                    "Faces crossed by the segment ({}, {}) do not form a cavity",
+                    start, end
+                ))
+            })?;
 
-        for edge in constrained_edges {
-            let a = start.borrow().position;
-            let b = end.borrow().position;
-            let c = edge.borrow().a.borrow().position;
-            let d = edge.borrow().b.borrow().position;
+        let removed_constraints = self.face_edge_constraints(&cavity);
 
-            if !edge.borrow().crep.is_empty() {
-                let intersection_point = intersection_point(&(a, b), &(c, d)).unwrap();
+        for &face in cavity.iter() {
+            self.remove_face(face);
+        }
 
-                self.insert_point_on_edge(intersection_point, edge.clone());
+        for chain in [&top_vertices, &bottom_vertices] {
+            for face in self.triangulate_pseudo_polygon(chain) {
+                self.add_face(face);
             }
         }
 
-        let crossing_vertices = self.find_crossing_vertices(start.clone(), end.clone());
-
-        for i in 0..crossing_vertices.len() - 1 {
-            let start = crossing_vertices[i].clone();
-            let end = crossing_vertices[i + 1].clone();
-
-            // Check if start and end are connected by an edge
-            let edge = self
-                .edges
-                .iter()
-                .find(|edge| {
-                    let edge = edge.borrow();
-                    (edge.a.as_ptr() == start.as_ptr() && edge.b.as_ptr() == end.as_ptr())
-                        || (edge.a.as_ptr() == end.as_ptr() && edge.b.as_ptr() == start.as_ptr())
-                })
-                .cloned();
-
-            if let Some(edge) = edge {
-                edge.borrow_mut().insert_constraint(constraint_id);
-                continue;
+        let new_edge = self.find_edge(start, end).ok_or_else(|| {
+            CadetError::TopologyCorrupt("Inserted segment has no edge".to_string())
+        })?;
+        self.edges[new_edge].insert_constraint(constraint_id);
+        self.restore_edge_constraints(removed_constraints);
+        Ok(())
+    }
+
+    /// The boundary of a cavity split at `start` and `end` into the chains on
+    /// either side of the segment between them, both ordered from `start`.
+    ///
+    /// The chains are read from the end points of the crossed edges, in the
+    /// order the segment crosses them. A chain passes through a vertex twice
+    /// when the segment crosses edges on both sides of the faces around it
+    /// that it does not reach, which the boundary of the cavity alone cannot
+    /// tell apart from a vertex enclosed by the cavity.
+    fn cavity_chains(
+        &self,
+        cavity: &[FaceId],
+        start: VertexId,
+        end: VertexId,
+        crossed_edges: &[EdgeId],
+    ) -> Option<(Vec<VertexId>, Vec<VertexId>)> {
+        let a = self.vertices[start].position;
+        let b = self.vertices[end].position;
+
+        let mut top = vec![start];
+        let mut bottom = vec![start];
+
+        for &edge in crossed_edges {
+            let (u, w) = self.edges[edge].edge_indices();
+            let (above, below) = if orient2d(a, b, self.vertices[u].position) > 0. {
+                (u, w)
+            } else {
+                (w, u)
+            };
+
+            if top.last() != Some(&above) {
+                top.push(above);
             }
+            if bottom.last() != Some(&below) {
+                bottom.push(below);
+            }
+        }
 
-            let edge_list = self.find_crossing_edges(start.clone(), end.clone());
+        top.push(end);
+        bottom.push(end);
 
-            let mut top_vertices = Vec::new();
-            let mut bottom_vertices = Vec::new();
+        // Successive vertices of either chain are joined by an edge of a face
+        // of the cavity
+        let half_edges = cavity
+            .iter()
+            .flat_map(|&face| self.faces[face].edge_indices())
+            .collect::<HashSet<_>>();
 
-            for edge in edge_list.iter() {
-                let a = start.borrow().position;
-                let b = end.borrow().position;
-                let c = edge.borrow().a.borrow().position;
+        let is_chain = |chain: &[VertexId]| {
+            chain.windows(2).all(|pair| {
+                half_edges.contains(&(pair[0], pair[1])) || half_edges.contains(&(pair[1], pair[0]))
+            })
+        };
 
-                let edge_indices = edge.borrow().edge_indices();
+        (is_chain(&top) && is_chain(&bottom)).then_some((top, bottom))
+    }
 
-                // Delete all triangles that contain the edge
-                let sym_edge = self
-                    .get_sym_edge_for_half_edge(&edge_indices)
-                    .or_else(|| self.get_sym_edge_for_half_edge(&edge_indices.flipped()));
+    /// Delaunay triangulation of the polygon formed by a chain of vertices on
+    /// one side of an inserted segment, closed by the segment itself.
+    ///
+    /// The apex of each triangle is the chain vertex whose circumcircle with
+    /// the base contains no other vertex of the chain, and both sides of it
+    /// are triangulated recursively.
+    fn triangulate_pseudo_polygon(&self, chain: &[VertexId]) -> Vec<[VertexId; 3]> {
+        if chain.len() < 3 {
+            return Vec::new();
+        }
 
-                let sym_edge = match sym_edge {
-                    Some(sym_edge) => sym_edge,
-                    None => {
-                        continue;
-                    }
-                };
+        let position = |v: VertexId| self.vertices[v].position;
+        let a = chain[0];
+        let b = chain[chain.len() - 1];
+
+        let mut apex = 1;
+        for i in 2..chain.len() - 1 {
+            if in_circumcircle(
+                position(a),
+                position(b),
+                position(chain[apex]),
+                position(chain[i]),
+            ) {
+                apex = i;
+            }
+        }
 
-                let face_1 = sym_edge.borrow().face.clone();
-                let face_2 = sym_edge.borrow().neighbor_face();
+        let c = chain[apex];
 
-                let face_2 = match face_2 {
-                    Some(face) => face,
-                    None => {
-                        continue;
-                    }
-                };
+        // Faces are wound clockwise
+        let face = if orient2d(position(a), position(b), position(c)) > 0. {
+            [a, c, b]
+        } else {
+            [a, b, c]
+        };
 
-                self.remove_face(face_1.clone());
-                self.remove_face(face_2.clone());
+        let mut faces = vec![face];
+        faces.extend(self.triangulate_pseudo_polygon(&chain[..=apex]));
+        faces.extend(self.triangulate_pseudo_polygon(&chain[apex..]));
 
-                let is_ccw = is_ccw(&a, &b, &c) == Orientation::CounterClockwise;
+        faces
+    }
 
-                if is_ccw {
-                    top_vertices.push(edge.borrow().a.clone());
-                    bottom_vertices.push(edge.borrow().b.clone());
-                } else {
-                    top_vertices.push(edge.borrow().b.clone());
-                    bottom_vertices.push(edge.borrow().a.clone());
+    /// Vertices on the segment from `start` to `end` and edges properly
+    /// crossed by it, ordered from `start`
+    fn segment_crossings(&self, start: VertexId, end: VertexId) -> Vec<SegmentCrossing> {
+        self.walk_segment(start, end)
+            .unwrap_or_else(|| self.segment_crossings_brute_force(start, end))
+    }
+
+    /// Walks the corridor of faces along the segment from `start` to `end`.
+    ///
+    /// Returns the vertices on the segment and the edges crossed by it in the
+    /// order they are met, or `None` if the walk leaves the triangulation.
+    /// Vertices on the segment split the walk, which continues from each of
+    /// them.
+    fn walk_segment(&self, start: VertexId, end: VertexId) -> Option<Vec<SegmentCrossing>> {
+        let a = self.vertices[start].position;
+        let b = self.vertices[end].position;
+
+        let side = |v: VertexId| is_ccw(&a, &b, &self.vertices[v].position);
+
+        let mut crossings = vec![SegmentCrossing::Vertex(start)];
+        let mut vertex = start;
+
+        for _ in 0..=self.edges.len() {
+            // Find the face around the vertex that the segment leaves through
+            let v = self.vertices[vertex].position;
+            let mut crossed = None;
+            let mut next_vertex = None;
+
+            for &sym_edge in self.sym_edges_by_vertices.get(&vertex)? {
+                let nxt = self.sym_edges[sym_edge].nxt?;
+                let vb = self.sym_edge_b(sym_edge);
+                let vc = self.sym_edge_b(nxt);
+
+                let pb = self.vertices[vb].position;
+                let pc = self.vertices[vc].position;
+
+                // On the boundary, the vertex after `vertex` is only the last
+                // vertex of its face
+                let on_segment = [vb, vc].into_iter().find(|&u| {
+                    side(u) == Orientation::Collinear
+                        && (self.vertices[u].position - v).dot(b - v) > 0.
+                });
+                if on_segment.is_some() {
+                    next_vertex = on_segment;
+                    break;
+                }
+
+                let wedge = is_ccw(&v, &pb, &pc);
+                let is_in_wedge = is_ccw(&v, &pb, &b) == wedge && is_ccw(&v, &b, &pc) == wedge;
+
+                if is_in_wedge {
+                    crossed = Some(nxt);
+                    break;
                 }
             }
 
-            if top_vertices.is_empty() || bottom_vertices.is_empty() {
+            if let Some(next_vertex) = next_vertex {
+                crossings.push(SegmentCrossing::Vertex(next_vertex));
+                if next_vertex == end {
+                    return Some(crossings);
+                }
+                vertex = next_vertex;
                 continue;
             }
 
-            let mut new_faces = Vec::new();
+            // Cross edges until the segment meets the next vertex
+            let mut sym_edge = self.neighbor(crossed?)?;
+
+            loop {
+                // The segment passes through an end point of the edge rather
+                // than crossing it, and is walked on from there
+                let (u, w) = self.sym_edge_indices(sym_edge);
+                if let Some(on_segment) = [u, w]
+                    .into_iter()
+                    .find(|&v| side(v) == Orientation::Collinear)
+                {
+                    crossings.push(SegmentCrossing::Vertex(on_segment));
+                    if on_segment == end {
+                        return Some(crossings);
+                    }
+                    vertex = on_segment;
+                    break;
+                }
 
-            for i in 0..top_vertices.len() - 1 {
-                let v = top_vertices[i].clone();
-                let vs = top_vertices[i + 1].clone();
+                crossings.push(SegmentCrossing::Edge(self.sym_edges[sym_edge].edge));
 
-                new_faces.push(self.add_face([start.clone(), v.clone(), vs.clone()]));
-            }
+                let nxt = self.sym_edges[sym_edge].nxt?;
+                let x = self.sym_edge_b(nxt);
 
-            for i in 0..bottom_vertices.len() - 1 {
-                let v = bottom_vertices[i].clone();
-                let vs = bottom_vertices[i + 1].clone();
+                if x == end || side(x) == Orientation::Collinear {
+                    crossings.push(SegmentCrossing::Vertex(x));
+                    if x == end {
+                        return Some(crossings);
+                    }
+                    vertex = x;
+                    break;
+                }
 
-                new_faces.push(self.add_face([start.clone(), vs.clone(), v.clone()]));
-            }
+                let next = if side(w) != side(x) {
+                    nxt
+                } else {
+                    self.sym_edges[nxt].nxt?
+                };
 
-            // Insert the finsishing triangles
-            let v = top_vertices.last().unwrap().clone();
-            let vs = bottom_vertices.last().unwrap().clone();
-
-            let face_1 = self.add_face([start.clone(), end.clone(), v.clone()]);
-            let face_2 = self.add_face([end.clone(), start.clone(), vs.clone()]);
-
-            new_faces.push(face_1.clone());
-            new_faces.push(face_2.clone());
-
-            let new_edge = self
-                .get_sym_edge_for_half_edge(&face_1.borrow().edge_indices()[0])
-                .unwrap();
-            let new_edge = new_edge.borrow().edge.clone();
-            new_edge.borrow_mut().insert_constraint(constraint_id);
-
-            let mut edges = VecDeque::new();
-
-            for i in new_faces.iter() {
-                let face = i.borrow();
-                for edge in face.edge_indices().iter() {
-                    edges.push_back(
-                        self.get_sym_edge_for_half_edge(edge)
-                            .unwrap()
-                            .borrow()
-                            .edge
-                            .clone(),
-                    );
+                if crossings.len() > self.edges.len() {
+                    return None;
                 }
-            }
 
-            self.flip_edges(v.clone(), &mut edges);
+                sym_edge = self.neighbor(next)?;
+            }
         }
+
+        None
     }
 
-    fn find_crossing_edges(
+    fn segment_crossings_brute_force(
         &self,
-        start: Rc<RefCell<Vertex>>,
-        end: Rc<RefCell<Vertex>>,
-    ) -> Vec<Rc<RefCell<Edge>>> {
-        let mut edge_list = Vec::new();
-
-        for edge in self.edges.iter() {
-            let edge_borrowed = edge.borrow();
-
-            let is_crossing = is_crossing(
-                &(start.borrow().position, end.borrow().position),
-                &(
-                    edge_borrowed.a.borrow().position,
-                    edge_borrowed.b.borrow().position,
-                ),
+        start: VertexId,
+        end: VertexId,
+    ) -> Vec<SegmentCrossing> {
+        let a = self.vertices[start].position;
+        let b = self.vertices[end].position;
+        let segment = (a, b);
+
+        let edge_crossings = self.edges.iter().filter_map(|(id, edge)| {
+            let edge_segment = (
+                self.vertices[edge.a].position,
+                self.vertices[edge.b].position,
             );
-
-            if is_crossing {
-                edge_list.push(edge.clone());
+            if !is_crossing(&segment, &edge_segment) {
+                return None;
             }
-        }
+            let intersection = intersection_point(&segment, &edge_segment)?;
+            Some((SegmentCrossing::Edge(id), (intersection - a).length()))
+        });
 
-        //Sort edge list by the distance of the start vertex and the intersection point
-        edge_list.sort_by(|a, b| {
-            let a = a.borrow();
-            let b = b.borrow();
+        let vertex_crossings = self.vertices.iter().filter_map(|(id, v)| {
+            let p = v.position;
 
-            let a = intersection_point(
-                &(start.borrow().position, end.borrow().position),
-                &(a.a.borrow().position, a.b.borrow().position),
-            )
-            .unwrap();
+            if orient2d(a, b, p) != 0. {
+                return None;
+            }
+            if p.x < a.x.min(b.x) || p.x > a.x.max(b.x) {
+                return None;
+            }
+            if p.y < a.y.min(b.y) || p.y > a.y.max(b.y) {
+                return None;
+            }
 
-            let b = intersection_point(
-                &(start.borrow().position, end.borrow().position),
-                &(b.a.borrow().position, b.b.borrow().position),
-            )
-            .unwrap();
+            Some((SegmentCrossing::Vertex(id), (p - a).length()))
+        });
 
-            let a_length = (a - start.borrow().position).length();
-            let b_length = (b - start.borrow().position).length();
+        let mut crossings = edge_crossings.chain(vertex_crossings).collect::<Vec<_>>();
 
-            a_length.partial_cmp(&b_length).unwrap()
-        });
+        //Sort the crossings by their distance from the start vertex
+        crossings.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
 
-        // // Get the initial half-edge from the starting vertex
-        // let sym_edge = self
-        //     .sym_edges_by_vertices
-        //     .get(&start.borrow().index)
-        //     .and_then(|edges| edges.first().cloned());
-
-        // if sym_edge.is_none() {
-        //     return edge_list; // If no half-edges are associated with the vertex, return empty
-        // }
-
-        // let mut sym_edge = sym_edge.unwrap();
-        // let initial_sym_edge = sym_edge.clone(); // Keep track of the initial half-edge to detect cycles
-        // let mut visited = std::collections::HashSet::new(); // Prevent re-checking edges
-
-        // loop {
-        //     let current_edge = sym_edge.clone();
-
-        //     println!(
-        //         "Current edge: {:?}",
-        //         (
-        //             current_edge.borrow().edge.borrow().a.borrow().position,
-        //             current_edge.borrow().edge.borrow().b.borrow().position
-        //         )
-        //     );
-
-        //     // If the edge is already visited, break
-        //     let current_index = current_edge.as_ptr() as usize;
-        //     if !visited.insert(current_index) {
-        //         break;
-        //     }
-
-        //     // Check if the current edge crosses the segment
-        //     let is_crossing = is_crossing(
-        //         &(start.borrow().position, end.borrow().position),
-        //         &(
-        //             current_edge.borrow().edge.borrow().a.borrow().position,
-        //             current_edge.borrow().edge.borrow().b.borrow().position,
-        //         ),
-        //     );
-
-        //     if is_crossing {
-        //         edge_list.push(current_edge.borrow().edge.clone());
-        //     }
-
-        //     // Traverse the `nxt` edge first
-        //     let mut next_edge = current_edge.borrow().nxt.clone();
-
-        //     // If `nxt` is None or already visited, traverse the `rot` edge
-        //     if next_edge.is_none()
-        //         || visited.contains(&(next_edge.as_ref().unwrap().as_ptr() as usize))
-        //     {
-        //         next_edge = current_edge.borrow().rot.clone();
-        //     }
-
-        //     // If no new edge to traverse, or we've cycled back to the starting edge, stop
-        //     if next_edge.is_none()
-        //         || next_edge.as_ref().map(|e| e.as_ptr()) == Some(initial_sym_edge.as_ptr())
-        //     {
-        //         break;
-        //     }
-
-        //     sym_edge = next_edge.unwrap();
-        // }
-
-        edge_list
+        crossings
+            .into_iter()
+            .map(|(crossing, _)| crossing)
+            .collect()
     }
+}
 
-    fn find_crossing_vertices(
-        &self,
-        start: Rc<RefCell<Vertex>>,
-        end: Rc<RefCell<Vertex>>,
-    ) -> Vec<Rc<RefCell<Vertex>>> {
-        let mut vertex_list = Vec::new();
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
 
-        let a = start.borrow().position;
-        let b = end.borrow().position;
+    use crate::{constraints::constraints::ConstraintSegment, locate_result::LocateResult};
 
-        for v in self.vertices.iter() {
-            let p = v.borrow().position;
-            let cross_product = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    use super::{SegmentCrossing, CDT};
 
-            if cross_product.abs() > 1e-9 {
-                continue;
-            }
+    fn grid(n: usize) -> Vec<DVec2> {
+        let step = 2. / (n - 1) as f64;
 
-            if p.x < a.x.min(b.x) || p.x > a.x.max(b.x) {
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| DVec2::new(i as f64, j as f64) * step - 1.))
+            .collect()
+    }
+
+    fn polyline(points: Vec<DVec2>, id: usize) -> ConstraintSegment {
+        ConstraintSegment {
+            constraints: points,
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn walks_continue_through_vertices_on_the_segment() {
+        let points = grid(9);
+        let cdt = CDT::from_points(&points).unwrap();
+        let vertex_at = |p: DVec2| match cdt.locate_point(&p) {
+            LocateResult::Vertex(vertex) => vertex,
+            result => panic!("{:?} is not a vertex but {:?}", p, result),
+        };
+
+        // The diagonal passes through seven vertices between its end points
+        let (start, end) = (vertex_at(DVec2::splat(-1.)), vertex_at(DVec2::ONE));
+        let crossings = cdt.walk_segment(start, end).unwrap();
+        let vertices = crossings
+            .iter()
+            .filter(|crossing| matches!(crossing, SegmentCrossing::Vertex(_)))
+            .count();
+        assert_eq!(vertices, 9);
+        assert_eq!(crossings, cdt.segment_crossings_brute_force(start, end));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let start = vertex_at(points[rng.gen_range(0..points.len())]);
+            let end = vertex_at(points[rng.gen_range(0..points.len())]);
+            if start == end {
                 continue;
             }
-            if p.y < a.y.min(b.y) || p.y > a.y.max(b.y) {
-                continue;
+
+            assert_eq!(
+                cdt.walk_segment(start, end),
+                Some(cdt.segment_crossings_brute_force(start, end))
+            );
+        }
+    }
+
+    #[test]
+    fn crossing_segments_share_a_steiner_vertex() {
+        let mut cdt = CDT::from_points(&grid(5)).unwrap();
+
+        cdt.insert_constraint(&polyline(
+            vec![DVec2::new(-0.9, -0.7), DVec2::new(0.8, 0.6)],
+            0,
+        ))
+        .unwrap();
+        let intersections = cdt
+            .insert_constraint(&polyline(
+                vec![DVec2::new(-0.7, 0.9), DVec2::new(0.6, -0.8)],
+                1,
+            ))
+            .unwrap();
+
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].constraints, vec![0, 1]);
+
+        let vertex = intersections[0].vertex;
+        assert!(cdt.vertices[vertex].steiner);
+        // The input points of the constraints are not Steiner vertices
+        assert_eq!(cdt.vertices.values().filter(|v| v.steiner).count(), 1);
+        assert_eq!(cdt.vertices[vertex].constraints, 2);
+        assert!(cdt.constraints[&0].contains(&vertex));
+        assert!(cdt.constraints[&1].contains(&vertex));
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+    }
+
+    #[test]
+    fn random_crossing_polylines_keep_the_triangulation_delaunay() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut random_point = |r: f64| DVec2::new(rng.gen_range(-r..r), rng.gen_range(-r..r));
+
+            let mut points = vec![
+                DVec2::new(-1., -1.),
+                DVec2::new(1., -1.),
+                DVec2::new(1., 1.),
+                DVec2::new(-1., 1.),
+            ];
+            points.extend((0..200).map(|_| random_point(1.)));
+            let mut cdt = CDT::from_points(&points).unwrap();
+
+            for id in 0..15 {
+                let constraint = polyline((0..5).map(|_| random_point(0.9)).collect(), id);
+                cdt.insert_constraint(&constraint).unwrap();
             }
 
-            vertex_list.push(v.clone());
+            let report = cdt.validate();
+            assert!(report.is_valid(), "seed {}: {}", seed, report);
+            assert!(report.non_delaunay_edges.is_empty(), "seed {}", seed);
         }
+    }
 
-        //Sort vertex list by the distance of the start vertex and the intersection point
-        vertex_list.sort_by(|a, b| {
-            let a = a.borrow().position;
-            let b = b.borrow().position;
+    #[test]
+    fn constraints_through_grid_vertices_keep_the_triangulation_delaunay() {
+        let points = grid(11);
+
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut cdt = CDT::from_points(&points).unwrap();
+
+            for id in 0..20 {
+                let constraint = ConstraintSegment {
+                    closed: rng.gen_bool(0.5),
+                    ..polyline(
+                        (0..4)
+                            .map(|_| points[rng.gen_range(0..points.len())])
+                            .collect(),
+                        id,
+                    )
+                };
+                cdt.insert_constraint(&constraint).unwrap();
+            }
 
-            let a_length = (a - start.borrow().position).length();
-            let b_length = (b - start.borrow().position).length();
+            let report = cdt.validate();
+            assert!(report.is_valid(), "seed {}: {}", seed, report);
+            assert!(report.non_delaunay_edges.is_empty(), "seed {}", seed);
+        }
+    }
 
-            a_length.partial_cmp(&b_length).unwrap()
-        });
+    #[test]
+    fn overlapping_loops_keep_the_triangulation_delaunay() {
+        let mut cdt = CDT::from_points(&grid(11)).unwrap();
 
-        vertex_list
+        cdt.insert_constraint(&ConstraintSegment::generate_circle(DVec2::ZERO, 0.5, 30, 0))
+            .unwrap();
+        cdt.insert_constraint(&ConstraintSegment::generate_square(
+            DVec2::new(0.3, 0.1),
+            0.8,
+            1,
+        ))
+        .unwrap();
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
     }
 }
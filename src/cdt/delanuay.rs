@@ -1,143 +1,522 @@
-use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use std::collections::{HashMap, VecDeque};
 
-use glam::{DMat3, DVec2, DVec3};
+use glam::DVec2;
 
 use crate::{
+    arena::Handle,
     cdt::location::FastLocate,
-    edge::Edge,
+    edge::EdgeId,
+    error::{CadetError, CadetResult},
+    face::FaceId,
+    helper::is_ccw,
+    locate_result::LocateResult,
+    orientation::Orientation,
+    predicates::in_circumcircle,
     symmetric_compare::{Flipped, SymmetricCompare},
-    vertex::Vertex,
+    vertex::VertexId,
 };
 
 use super::cdt::CDT;
 
+/// Number of bootstrap vertices added in front of the input points
+const SUPER_TRIANGLE_VERTICES: usize = 3;
+
 impl CDT {
+    /// Builds the Delaunay triangulation of a point set by incremental insertion.
+    ///
+    /// The points are inserted into a super-triangle enclosing all of them, which
+    /// is removed afterwards. Duplicate points are merged into a single vertex.
+    /// Fails if the points are all collinear, as they do not span any face.
+    pub fn from_points(points: &[DVec2]) -> CadetResult<Self> {
+        let mut cdt = Self::from_super_triangle(points);
+
+        for point in points {
+            match cdt.locate_point(point) {
+                LocateResult::Edge(edge) => {
+                    cdt.insert_point_on_edge(*point, edge);
+                }
+                LocateResult::Face(face) => {
+                    cdt.insert_point_in_face(*point, face);
+                }
+                LocateResult::Vertex(_) | LocateResult::None => {}
+            }
+        }
+
+        let mut cdt = cdt.without_super_triangle();
+
+        if cdt.faces.is_empty() {
+            return Err(CadetError::DegeneratePoints);
+        }
+
+        cdt.fill_hull_pockets();
+
+        Ok(cdt)
+    }
+
+    /// Creates a triangulation consisting of a single triangle that encloses
+    /// the bounding box of `points` with a wide margin.
+    fn from_super_triangle(points: &[DVec2]) -> Self {
+        let (min, max) = points.iter().fold(
+            (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let (min, max) = if points.is_empty() {
+            (DVec2::ZERO, DVec2::ZERO)
+        } else {
+            (min, max)
+        };
+
+        let center = (min + max) / 2.;
+        let size = (max - min).max_element().max(1.);
+
+        let mut cdt = CDT::default();
+
+        let a = cdt.add_vertex(center + DVec2::new(-20. * size, -size), 0);
+        let b = cdt.add_vertex(center + DVec2::new(20. * size, -size), 0);
+        let c = cdt.add_vertex(center + DVec2::new(0., 20. * size), 0);
+
+        // Faces are wound clockwise, matching the projected glTF meshes
+        cdt.add_face([a, c, b]);
+
+        cdt
+    }
+
+    /// Rebuilds the triangulation without the super-triangle vertices and the
+    /// faces incident to them, keeping the insertion order of the other vertices.
+    fn without_super_triangle(&self) -> Self {
+        let is_super_vertex = |vertex: &VertexId| vertex.index() < SUPER_TRIANGLE_VERTICES;
+
+        let faces = self
+            .faces
+            .values()
+            .map(|face| face.vertex_indices())
+            .filter(|indices| !indices.iter().any(is_super_vertex))
+            .collect::<Vec<_>>();
+
+        let mut cdt = CDT::default();
+        let mut vertex_map = HashMap::new();
+
+        for (id, vertex) in self.vertices.iter() {
+            if is_super_vertex(&id) {
+                continue;
+            }
+
+            let is_used = self.sym_edges_by_vertices.contains_key(&id);
+            if is_used {
+                let new_vertex = cdt.add_vertex(vertex.position, vertex.constraints);
+                cdt.vertices[new_vertex].height = vertex.height;
+                vertex_map.insert(id, new_vertex);
+            }
+        }
+
+        for face in faces {
+            cdt.add_face(face.map(|i| vertex_map[&i]));
+        }
+
+        cdt
+    }
+
+    /// Closes the concave pockets left on the boundary after removing the
+    /// super-triangle, so that the triangulation covers the convex hull.
+    ///
+    /// The boundary is walked once from a vertex of the convex hull, keeping
+    /// a stack of the vertices passed so far like a Graham scan; every
+    /// concave corner on top of the stack is closed with a face.
+    fn fill_hull_pockets(&mut self) {
+        // Boundary half-edges have the interior on their right, so the walk
+        // along them turns clockwise at convex corners
+        let next = self
+            .sym_edges_by_half_edges
+            .keys()
+            .filter(|edge| !self.sym_edges_by_half_edges.contains_key(&edge.flipped()))
+            .copied()
+            .collect::<HashMap<_, _>>();
+
+        // The lowest of the leftmost vertices is on the convex hull
+        let Some(start) = next.keys().copied().min_by(|&a, &b| {
+            let (pa, pb) = (self.vertices[a].position, self.vertices[b].position);
+            pa.x.total_cmp(&pb.x).then(pa.y.total_cmp(&pb.y))
+        }) else {
+            return;
+        };
+
+        let mut stack = vec![start];
+        let mut vertex = start;
+
+        for _ in 0..next.len() {
+            vertex = next[&vertex];
+            stack.push(vertex);
+
+            while let [.., a, b, c] = stack[..] {
+                let [pa, pb, pc] = [a, b, c].map(|v| self.vertices[v].position);
+                let is_concave = is_ccw(&pa, &pb, &pc) == Orientation::CounterClockwise;
+
+                if !is_concave {
+                    break;
+                }
+
+                self.add_face([a, c, b]);
+
+                // The closed corner is opposite the new boundary edge, so only
+                // the two edges it had before can be illegal
+                let edge_stack = [(c, b), (b, a)]
+                    .into_iter()
+                    .filter_map(|(u, w)| self.find_edge(u, w))
+                    .collect();
+
+                self.legalize_edges(edge_stack);
+
+                stack.remove(stack.len() - 2);
+            }
+
+            if vertex == start {
+                break;
+            }
+        }
+    }
+
     // Check if an edge is Delaunay using the in-circle test
     pub fn is_delaunay(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> bool {
-        let matrix: DMat3 = DMat3::from_cols(
-            DVec3::new(a.x - d.x, a.y - d.y, (a - d).dot(a - d)),
-            DVec3::new(b.x - d.x, b.y - d.y, (b - d).dot(b - d)),
-            DVec3::new(c.x - d.x, c.y - d.y, (c - d).dot(c - d)),
-        );
-        let det = matrix.determinant();
+        !in_circumcircle(a, b, c, d)
+    }
+
+    /// Whether the edge is constrained, on the boundary, or its opposite
+    /// vertices lie outside of each other's circumcircle
+    pub(crate) fn is_edge_delaunay(&self, edge: EdgeId) -> bool {
+        let edge = &self.edges[edge];
+
+        if edge.is_constrained() {
+            return true;
+        }
+
+        let (a, b) = edge.edge_indices();
+        let (f1, f2) = match (
+            self.get_sym_edge_for_half_edge(&(a, b)),
+            self.get_sym_edge_for_half_edge(&(b, a)),
+        ) {
+            (Some(s1), Some(s2)) => (self.sym_edges[s1].face, self.sym_edges[s2].face),
+            _ => return true,
+        };
+
+        let [p1, p2, p3] = self.faces[f1].vertices.map(|v| self.vertices[v].position);
+        let o = self.faces[f2].opposite_vertex(edge);
+
+        Self::is_delaunay(p1, p2, p3, self.vertices[o].position)
+    }
+
+    /// Whether every unconstrained edge of the triangulation is Delaunay
+    pub fn is_constrained_delaunay(&self) -> bool {
+        self.edges.ids().all(|edge| self.is_edge_delaunay(edge))
+    }
+
+    /// Flips unconstrained edges until the whole triangulation is constrained
+    /// Delaunay, and returns the number of flips.
+    ///
+    /// Unlike [`CDT::flip_edges`], which only visits the edges around an
+    /// inserted point, this starts from every edge, so it also repairs
+    /// imported meshes that were not Delaunay to begin with.
+    pub fn make_delaunay(&mut self) -> usize {
+        let edge_stack = self.edges.ids().collect();
+        self.legalize_edges(edge_stack)
+    }
+
+    /// Flips the edges of the stack that are not Delaunay, along with the
+    /// edges around every flipped one, and returns the number of flips
+    pub(crate) fn legalize_edges(&mut self, mut edge_stack: VecDeque<EdgeId>) -> usize {
+        let mut flips = 0;
 
-        det >= -1e-8
+        while let Some(e) = edge_stack.pop_front() {
+            // Edges removed by an earlier flip are skipped
+            if !self.edges.contains(e) || self.is_edge_delaunay(e) {
+                continue;
+            }
+
+            let (a, b) = self.edges[e].edge_indices();
+            let (c, d) = self.flip_edge(e);
+            flips += 1;
+
+            for (u, w) in [(a, c), (c, b), (b, d), (d, a)] {
+                edge_stack.extend(self.find_edge(u, w));
+            }
+        }
+
+        flips
     }
 
     // Edge-flipping routine
-    pub fn flip_edges(
-        &mut self,
-        p: Rc<RefCell<Vertex>>,
-        edge_stack: &mut VecDeque<Rc<RefCell<Edge>>>,
-    ) {
+    pub fn flip_edges(&mut self, p: VertexId, edge_stack: &mut VecDeque<EdgeId>) {
         while let Some(e) = edge_stack.pop_front() {
-            {
-                let e_borrowed = e.borrow();
-                if e_borrowed.is_constrained() {
+            // Edges removed by an earlier flip are skipped
+            let edge = match self.edges.get(e) {
+                Some(edge) => edge.clone(),
+                None => continue,
+            };
+
+            if edge.is_constrained() {
+                continue;
+            }
+
+            let sym_edge = self
+                .get_sym_edge_for_half_edge(&edge.edge_indices())
+                .or_else(|| self.get_sym_edge_for_half_edge(&edge.edge_indices().flipped()));
+
+            let sym_edge = match sym_edge {
+                Some(sym_edge) => sym_edge,
+                None => {
                     continue;
                 }
+            };
 
-                let sym_edge_rc = self.get_sym_edge_for_half_edge(&e_borrowed.edge_indices());
-
-                let sym_edge_rc = match sym_edge_rc {
-                    Some(sym_edge) => Some(sym_edge),
-                    None => self.get_sym_edge_for_half_edge(&e_borrowed.edge_indices().flipped()),
-                };
-
-                let sym_edge_rc = match sym_edge_rc {
-                    Some(sym_edge) => sym_edge,
-                    None => {
-                        continue;
-                    }
-                };
-
-                let sym_edge = sym_edge_rc.borrow();
-
-                let neighbor_face_rc = match sym_edge.neighbor_face() {
-                    Some(face) => face,
-                    None => {
-                        continue;
-                    }
-                };
-
-                let mut face_borrowed = sym_edge.face.borrow_mut();
-                let mut neighbor_face = neighbor_face_rc.borrow_mut();
-
-                let tri = geo::Triangle::new(
-                    geo::Coord {
-                        x: face_borrowed.vertices[0].borrow().position.x,
-                        y: face_borrowed.vertices[0].borrow().position.y,
-                    },
-                    geo::Coord {
-                        x: face_borrowed.vertices[1].borrow().position.x,
-                        y: face_borrowed.vertices[1].borrow().position.y,
-                    },
-                    geo::Coord {
-                        x: face_borrowed.vertices[2].borrow().position.x,
-                        y: face_borrowed.vertices[2].borrow().position.y,
-                    },
-                );
-
-                //If point is inside face, swap the faces
-                if tri.locate_point(&p.borrow().position) {
-                    std::mem::swap(&mut face_borrowed, &mut neighbor_face);
+            let neighbor_face = match self.neighbor_face(sym_edge) {
+                Some(face) => face,
+                None => {
+                    continue;
                 }
+            };
 
-                let o_vertex = neighbor_face.opposite_vertex(&e_borrowed);
-                let o = o_vertex.borrow();
-                let is_delanuay = Self::is_delaunay(
-                    face_borrowed.vertices[0].borrow().position,
-                    face_borrowed.vertices[1].borrow().position,
-                    face_borrowed.vertices[2].borrow().position,
-                    o.position,
-                );
+            let mut face = &self.faces[self.sym_edges[sym_edge].face];
+            let mut neighbor_face = &self.faces[neighbor_face];
 
-                if is_delanuay {
-                    continue;
-                }
+            let positions = face.vertices.map(|v| self.vertices[v].position);
 
-                let different_edges = face_borrowed
-                    .edge_indices()
-                    .iter()
-                    .filter(|x| !(**x).symmetric_compare(&e_borrowed.edge_indices()))
-                    .map(|x| self.get_sym_edge_for_half_edge(x).unwrap())
-                    .map(|x| x.borrow().edge.clone())
-                    .collect::<Vec<_>>();
+            let tri = geo::Triangle::new(
+                geo::Coord {
+                    x: positions[0].x,
+                    y: positions[0].y,
+                },
+                geo::Coord {
+                    x: positions[1].x,
+                    y: positions[1].y,
+                },
+                geo::Coord {
+                    x: positions[2].x,
+                    y: positions[2].y,
+                },
+            );
+
+            //If point is inside face, swap the faces
+            if tri.locate_point(&self.vertices[p].position) {
+                std::mem::swap(&mut face, &mut neighbor_face);
+            }
 
-                assert_eq!(different_edges.len(), 2);
+            let o = neighbor_face.opposite_vertex(&edge);
+            let is_delanuay = Self::is_delaunay(
+                self.vertices[face.vertices[0]].position,
+                self.vertices[face.vertices[1]].position,
+                self.vertices[face.vertices[2]].position,
+                self.vertices[o].position,
+            );
 
-                edge_stack.push_back(different_edges[0].clone());
-                edge_stack.push_back(different_edges[1].clone());
+            if is_delanuay {
+                continue;
             }
 
-            self.flip_edge(e.clone());
+            let different_edges = face
+                .edge_indices()
+                .iter()
+                .filter(|x| !(**x).symmetric_compare(&edge.edge_indices()))
+                .map(|x| self.find_edge(x.0, x.1).unwrap())
+                .collect::<Vec<_>>();
+
+            assert_eq!(different_edges.len(), 2);
+
+            edge_stack.push_back(different_edges[0]);
+            edge_stack.push_back(different_edges[1]);
+
+            self.flip_edge(e);
         }
     }
 
-    fn flip_edge(&mut self, edge: Rc<RefCell<Edge>>) {
+    /// Flips the edges of freshly built faces until they are Delaunay
+    pub(crate) fn flip_face_edges(&mut self, faces: &[FaceId]) {
+        let edge_stack = faces
+            .iter()
+            .flat_map(|&face| self.faces[face].edge_indices())
+            .map(|(a, b)| self.find_edge(a, b).unwrap())
+            .collect();
+
+        self.legalize_edges(edge_stack);
+    }
+
+    /// Replaces the edge by the other diagonal of its two faces, and returns
+    /// the end points of the new edge
+    fn flip_edge(&mut self, edge: EdgeId) -> (VertexId, VertexId) {
+        let edge = self.edges[edge].clone();
+
         let sym_edge = self
-            .get_sym_edge_for_half_edge(&edge.borrow().edge_indices())
+            .get_sym_edge_for_half_edge(&edge.edge_indices())
             .unwrap();
 
-        let f1 = sym_edge.borrow().face.clone();
-        let f2 = sym_edge.borrow().neighbor_face().unwrap();
+        let f1 = self.sym_edges[sym_edge].face;
+        let f2 = self.neighbor_face(sym_edge).unwrap();
 
-        // Az e élt nem tartalmazó csúcsok mindkét háromszögben
-        let v1 = f1.borrow().opposite_vertex(&edge.borrow());
-        let v2 = f2.borrow().opposite_vertex(&edge.borrow());
+        // The vertices of both faces that are not on the edge
+        let v1 = self.faces[f1].opposite_vertex(&edge);
+        let v2 = self.faces[f2].opposite_vertex(&edge);
 
         // Deleting the old faces
-        self.remove_face(f1.clone());
-        self.remove_face(f2.clone());
+        let removed_constraints = self.face_edge_constraints(&[f1, f2]);
+        self.remove_face(f1);
+        self.remove_face(f2);
 
         // Create two completely new faces
-        self.add_face([v2.clone(), v1.clone(), edge.borrow().a.clone()]);
+        self.add_face([v2, v1, edge.a]);
+
+        self.add_face([v1, v2, edge.b]);
 
-        self.add_face([v1.clone(), v2.clone(), edge.borrow().b.clone()]);
+        self.restore_edge_constraints(removed_constraints);
 
         // self.export_to_obj("./models/output.obj");
 
         // // Wait 100ms
         // std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        (v1, v2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use glam::DVec2;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        constraints::constraints::ConstraintSegment, error::CadetError, orientation::Orientation,
+        predicates::orientation,
+    };
+
+    use super::CDT;
+
+    #[test]
+    fn from_points_rejects_collinear_points() {
+        let points = [DVec2::ZERO, DVec2::new(1., 1.), DVec2::new(2., 2.)];
+
+        assert!(matches!(
+            CDT::from_points(&points),
+            Err(CadetError::DegeneratePoints)
+        ));
+        assert!(matches!(
+            CDT::from_points(&[]),
+            Err(CadetError::DegeneratePoints)
+        ));
+    }
+
+    #[test]
+    fn from_points_covers_the_convex_hull() {
+        // A flat cloud leaves pockets on its boundary once the super-triangle
+        // is removed
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = (0..1000)
+            .map(|_| DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-0.02..0.02)))
+            .collect::<Vec<_>>();
+
+        let cdt = CDT::from_points(&points).unwrap();
+        let report = cdt.validate();
+
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+
+        let next = cdt
+            .sym_edges_by_half_edges
+            .keys()
+            .filter(|&&(a, b)| !cdt.sym_edges_by_half_edges.contains_key(&(b, a)))
+            .copied()
+            .collect::<HashMap<_, _>>();
+
+        for (&a, &b) in next.iter() {
+            let [pa, pb, pc] = [a, b, next[&b]].map(|v| cdt.vertices[v].position);
+            assert_ne!(orientation(pa, pb, pc), Orientation::CounterClockwise);
+        }
+    }
+
+    #[test]
+    fn from_points_is_delaunay_after_filling_pockets() {
+        // Points along a side of the square leave pockets whose faces have
+        // to be flipped with the ones next to them
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut points = vec![
+                DVec2::new(-1., -1.),
+                DVec2::new(1., -1.),
+                DVec2::new(1., 1.),
+                DVec2::new(-1., 1.),
+            ];
+            points.extend(
+                (0..100).map(|_| DVec2::new(rng.gen_range(-1.0..-0.98), rng.gen_range(-1.0..1.0))),
+            );
+
+            let cdt = CDT::from_points(&points).unwrap();
+
+            assert!(cdt.is_constrained_delaunay(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn make_delaunay_flips_unconstrained_edges_back() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = (0..200)
+            .map(|_| DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect::<Vec<_>>();
+        let mut cdt = CDT::from_points(&points).unwrap();
+        cdt.insert_constraint(&ConstraintSegment::generate_circle(DVec2::ZERO, 0.5, 20, 0))
+            .unwrap();
+
+        let constrained = cdt
+            .edges
+            .values()
+            .filter(|edge| edge.is_constrained())
+            .map(|edge| edge.edge_indices())
+            .collect::<Vec<_>>();
+
+        // Flip unconstrained edges whose faces form a convex quadrilateral,
+        // the other diagonal is not Delaunay
+        let mut flipped = 0;
+        for edge in cdt.edges.ids().collect::<Vec<_>>() {
+            if flipped == 10 {
+                break;
+            }
+            if !cdt.edges.contains(edge) || cdt.edges[edge].is_constrained() {
+                continue;
+            }
+
+            let opposite = cdt
+                .get_all_sym_edges_for_edge(edge)
+                .into_iter()
+                .map(|sym_edge| {
+                    cdt.faces[cdt.sym_edges[sym_edge].face].opposite_vertex(&cdt.edges[edge])
+                })
+                .collect::<Vec<_>>();
+            if opposite.len() != 2 {
+                continue;
+            }
+
+            let (a, b) = cdt.edges[edge].edge_indices();
+            let [pa, pb, pc, pd] =
+                [a, b, opposite[0], opposite[1]].map(|v| cdt.vertices[v].position);
+            let sides = [orientation(pc, pd, pa), orientation(pc, pd, pb)];
+            if sides[0] == sides[1] || sides.contains(&Orientation::Collinear) {
+                continue;
+            }
+
+            cdt.flip_edge(edge);
+            flipped += 1;
+        }
+
+        assert_eq!(flipped, 10);
+        assert!(!cdt.is_constrained_delaunay());
+
+        assert!(cdt.make_delaunay() > 0);
+        assert!(cdt.is_constrained_delaunay());
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+
+        for (a, b) in constrained {
+            let edge = cdt.find_edge(a, b).unwrap();
+            assert!(cdt.edges[edge].crep.contains(&0));
+        }
     }
 }
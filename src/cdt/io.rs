@@ -1,88 +1,912 @@
-use std::{cell::RefCell, io::Write, rc::Rc};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    f64::consts::TAU,
+    io::Write,
+    path::Path,
+};
 
-use glam::DVec2;
+use glam::DVec3;
+use gltf::json::{
+    self,
+    validation::{Checked::Valid, USize64},
+};
 
-use crate::vertex::Vertex;
+use crate::{
+    error::{CadetError, CadetResult},
+    predicates::orient2d,
+    projection::Projection,
+    vertex::VertexId,
+};
 
 use super::cdt::CDT;
 
+/// Options for [`CDT::from_gltf_with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    /// Plane the model is projected onto
+    pub projection: Projection,
+    /// Name of the scene to load, all scenes are loaded if `None`
+    pub scene: Option<String>,
+    /// Names of the meshes to load, all meshes are loaded if empty
+    pub meshes: Vec<String>,
+    /// Vertices closer than this are merged into one. Coincident vertices
+    /// are always merged, also across primitives.
+    pub weld_distance: f64,
+}
+
+/// Merges vertices of the model that lie within the weld distance
+struct VertexWelder {
+    distance: f64,
+    cells: HashMap<[i64; 3], Vec<(DVec3, VertexId)>>,
+}
+
+impl VertexWelder {
+    fn new(distance: f64) -> Self {
+        Self {
+            distance,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Grid cell of the point. Without a distance, only points with the same
+    /// coordinates share a cell.
+    fn cell(&self, p: DVec3) -> [i64; 3] {
+        if self.distance > 0. {
+            (p / self.distance).floor().as_i64vec3().to_array()
+        } else {
+            // Adding zero turns -0.0 into 0.0
+            (p + DVec3::ZERO).to_array().map(|c| c.to_bits() as i64)
+        }
+    }
+
+    /// The vertex within the weld distance of `p`, added to the
+    /// triangulation if there is none
+    fn weld(&mut self, cdt: &mut CDT, p: DVec3) -> VertexId {
+        let [x, y, z] = self.cell(p);
+        let range = if self.distance > 0. { -1..=1 } else { 0..=0 };
+
+        let existing = range
+            .clone()
+            .flat_map(|dx| range.clone().map(move |dy| (dx, dy)))
+            .flat_map(|(dx, dy)| range.clone().map(move |dz| [x + dx, y + dy, z + dz]))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .find(|(q, _)| q.distance(p) <= self.distance)
+            .map(|&(_, vertex)| vertex);
+
+        if let Some(vertex) = existing {
+            return vertex;
+        }
+
+        let (position, height) = cdt.projection.project(p);
+        let vertex = cdt.add_vertex(position, 0);
+        cdt.vertices[vertex].height = height;
+
+        self.cells.entry([x, y, z]).or_default().push((p, vertex));
+
+        vertex
+    }
+}
+
 impl CDT {
-    pub fn from_gltf(model_path: &str) -> Self {
-        let scenes = easy_gltf::load(model_path).expect("Failed to load gltf file");
-        let first_scene = scenes.into_iter().next().expect("No scenes in gltf file");
-        let vertices = first_scene
-            .models
+    /// Loads a Y-up model, projected onto the XZ plane
+    pub fn from_gltf(model_path: &str) -> CadetResult<Self> {
+        Self::from_gltf_with_options(model_path, &ImportOptions::default())
+    }
+
+    /// Loads the triangles of the selected scenes and meshes, placed with
+    /// the world transforms of their nodes and projected onto the plane
+    /// chosen in the options. A plane whose axes do not span it is rejected.
+    ///
+    /// Faces are wound clockwise in the plane whatever their winding in the
+    /// model. Faces that welding collapses, and faces repeated by nodes shared
+    /// between scenes, are skipped.
+    ///
+    /// Models that do not form a flat manifold in the plane are rejected
+    /// with [`CadetError::TopologyCorrupt`]: faces without area after the
+    /// projection, edges shared by more than two faces, and faces that
+    /// overlap around a shared edge or vertex.
+    pub fn from_gltf_with_options(model_path: &str, options: &ImportOptions) -> CadetResult<Self> {
+        options.projection.check()?;
+
+        let scenes = easy_gltf::load(model_path).map_err(CadetError::Gltf)?;
+        let mut cdt = CDT {
+            projection: options.projection,
+            ..Default::default()
+        };
+
+        let is_selected_scene = |scene: &&easy_gltf::Scene| {
+            options
+                .scene
+                .as_ref()
+                .is_none_or(|name| scene.name.as_ref() == Some(name))
+        };
+        let is_selected_mesh = |model: &&easy_gltf::Model| {
+            options.meshes.is_empty()
+                || model
+                    .mesh_name()
+                    .is_some_and(|name| options.meshes.iter().any(|mesh| mesh == name))
+        };
+
+        let models = scenes
             .iter()
-            .flat_map(|m| m.vertices().clone())
-            .map(|v| (v.position.extend(1.), v.normal))
-            .enumerate()
-            .map(|(i, v)| {
-                let (p, _) = v;
-
-                Vertex {
-                    position: DVec2 {
-                        x: p.x as f64,
-                        y: p.z as f64,
-                    },
-                    index: i,
-                    constraints: 0,
+            .filter(is_selected_scene)
+            .flat_map(|scene| scene.models.iter())
+            .filter(is_selected_mesh);
+
+        let mut welder = VertexWelder::new(options.weld_distance);
+        let mut added_faces = HashSet::new();
+
+        for model in models {
+            // Indices refer to the vertices of their own primitive
+            let vertices = model
+                .vertices()
+                .iter()
+                .map(|v| {
+                    let p = v.position;
+                    welder.weld(&mut cdt, DVec3::new(p.x as f64, p.y as f64, p.z as f64))
+                })
+                .collect::<Vec<_>>();
+
+            for face in primitive_faces(model)? {
+                if face.iter().any(|&i| i >= vertices.len()) {
+                    return Err(CadetError::TopologyCorrupt(format!(
+                        "Face {:?} references a missing vertex",
+                        face
+                    )));
+                }
+
+                cdt.add_model_face(face.map(|i| vertices[i]), &mut added_faces)?;
+            }
+        }
+
+        if cdt.faces.is_empty() {
+            return Err(CadetError::EmptyModel);
+        }
+
+        cdt.check_model_fans()?;
+
+        Ok(cdt)
+    }
+
+    /// Loads a Y-up OBJ model, projected onto the XZ plane
+    pub fn from_obj(model_path: &str) -> CadetResult<Self> {
+        Self::from_obj_with_options(model_path, &ImportOptions::default())
+    }
+
+    /// Loads the faces of an OBJ model, projected onto the plane chosen in
+    /// the options. The scene and mesh selection only apply to glTF models.
+    ///
+    /// Polygons are split by ear clipping in the plane, or into a fan if
+    /// they are not simple there. Models are checked like in
+    /// [`CDT::from_gltf_with_options`]. Line elements are read separately by
+    /// [`Constraints::from_obj`].
+    ///
+    /// [`Constraints::from_obj`]: crate::Constraints::from_obj
+    pub fn from_obj_with_options(model_path: &str, options: &ImportOptions) -> CadetResult<Self> {
+        options.projection.check()?;
+
+        let obj = read_obj(model_path)?;
+        let mut cdt = CDT {
+            projection: options.projection,
+            ..Default::default()
+        };
+
+        let mut welder = VertexWelder::new(options.weld_distance);
+        let vertices = obj
+            .positions
+            .iter()
+            .map(|&(x, y, z, _)| welder.weld(&mut cdt, DVec3::new(x as f64, y as f64, z as f64)))
+            .collect::<Vec<_>>();
+
+        let mut added_faces = HashSet::new();
+
+        for polygon in obj.polygons.iter() {
+            let polygon = polygon_positions(polygon)
+                .into_iter()
+                .map(|i| vertices.get(i).copied().ok_or_else(missing_obj_vertex))
+                .collect::<CadetResult<Vec<_>>>()?;
+
+            for face in cdt.triangulate_polygon(&polygon) {
+                cdt.add_model_face(face, &mut added_faces)?;
+            }
+        }
+
+        if cdt.faces.is_empty() {
+            return Err(CadetError::EmptyModel);
+        }
+
+        cdt.check_model_fans()?;
+
+        Ok(cdt)
+    }
+
+    /// Adds a face of an imported model wound clockwise, unless welding
+    /// collapsed it or it was already added.
+    ///
+    /// Faces without area in the plane, edges shared by more than two faces
+    /// and faces on the same side of a shared edge are rejected, as the
+    /// SymEdge structure cannot represent them.
+    fn add_model_face(
+        &mut self,
+        face: [VertexId; 3],
+        added_faces: &mut HashSet<[VertexId; 3]>,
+    ) -> CadetResult<()> {
+        let mut key = face;
+        key.sort();
+
+        if key[0] == key[1] || key[1] == key[2] || !added_faces.insert(key) {
+            return Ok(());
+        }
+
+        let [a, b, c] = face.map(|v| self.vertices[v].position);
+        if orient2d(a, b, c) == 0. {
+            return Err(CadetError::TopologyCorrupt(format!(
+                "Face {:?} has no area in the projection plane",
+                face
+            )));
+        }
+
+        let face = self.clockwise(face);
+        let [a, b, c] = face;
+
+        for (a, b) in [(a, b), (b, c), (c, a)] {
+            if self.get_sym_edge_for_half_edge(&(a, b)).is_none() {
+                continue;
+            }
+
+            let message = if self.get_sym_edge_for_half_edge(&(b, a)).is_some() {
+                format!("Edge ({}, {}) is shared by more than two faces", a, b)
+            } else {
+                format!("Faces overlap along edge ({}, {})", a, b)
+            };
+            return Err(CadetError::TopologyCorrupt(message));
+        }
+
+        self.add_face(face);
+
+        Ok(())
+    }
+
+    /// Checks that the faces around every vertex of an imported model cover
+    /// disjoint angles in the plane, so the model does not fold over itself
+    fn check_model_fans(&self) -> CadetResult<()> {
+        // Counter-clockwise angle of the first edge of the face at the
+        // vertex, and the angle the face spans from there
+        let mut wedges: HashMap<VertexId, Vec<(f64, f64)>> = HashMap::new();
+
+        for face in self.faces.values() {
+            for i in 0..3 {
+                let vertex = face.vertices[i];
+                let p = self.vertices[vertex].position;
+                let next = self.vertices[face.vertices[(i + 1) % 3]].position - p;
+                let previous = self.vertices[face.vertices[(i + 2) % 3]].position - p;
+
+                let start = previous.to_angle().rem_euclid(TAU);
+                let span = previous.perp_dot(next).atan2(previous.dot(next));
+                wedges.entry(vertex).or_default().push((start, span));
+            }
+        }
+
+        for (vertex, mut wedges) in wedges {
+            wedges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            for (i, &(start, span)) in wedges.iter().enumerate() {
+                let next_start = match wedges.get(i + 1) {
+                    Some(&(next_start, _)) => next_start,
+                    None => wedges[0].0 + TAU,
+                };
+
+                if start + span > next_start + 1e-9 {
+                    return Err(CadetError::TopologyCorrupt(format!(
+                        "Faces overlap around vertex {}",
+                        vertex
+                    )));
                 }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Splits a polygon of an imported model into triangles, by ear clipping
+    /// if it is simple in the plane and into a fan otherwise
+    fn triangulate_polygon(&self, polygon: &[VertexId]) -> Vec<[VertexId; 3]> {
+        // Welding can merge successive vertices
+        let mut polygon = polygon.to_vec();
+        polygon.dedup();
+        if polygon.len() > 1 && polygon.first() == polygon.last() {
+            polygon.pop();
+        }
+
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+
+        // Ear clipping expects the clockwise winding of the faces
+        let doubled_area = polygon
+            .iter()
+            .zip(polygon.iter().cycle().skip(1))
+            .map(|(&a, &b)| {
+                self.vertices[a]
+                    .position
+                    .perp_dot(self.vertices[b].position)
+            })
+            .sum::<f64>();
+
+        if doubled_area > 0. {
+            polygon.reverse();
+        }
+
+        self.triangulate_hole(&polygon).unwrap_or_else(|| {
+            (1..polygon.len() - 1)
+                .map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+                .collect()
+        })
+    }
+
+    pub fn export_to_obj(&self, model_path: &str) -> CadetResult<()> {
+        self.projection.check()?;
+
+        let file = std::fs::File::create(model_path)?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        // OBJ indices are contiguous, removed vertices leave gaps in the arena
+        let mut obj_indices = HashMap::new();
+
+        for (id, vertex) in self.vertices.iter() {
+            obj_indices.insert(id, obj_indices.len() + 1);
+            let p = self.projection.unproject(vertex.position, vertex.height);
+            writeln!(writer, "v {} {} {}", p.x, p.y, p.z)?;
+        }
+
+        for face in self.faces.values() {
+            let indices = self
+                .exported_winding(face.vertex_indices())
+                .map(|v| obj_indices[&v]);
+            writeln!(writer, "f {} {} {}", indices[0], indices[1], indices[2])?;
+        }
+
+        writer.flush()?;
+
+        // println!("Exported to {}", model_path);
+
+        Ok(())
+    }
+
+    /// The face with its vertices in the order of a clockwise winding
+    fn clockwise(&self, face: [VertexId; 3]) -> [VertexId; 3] {
+        let [a, b, c] = face.map(|v| self.vertices[v].position);
+
+        if orient2d(a, b, c) > 0. {
+            [face[0], face[2], face[1]]
+        } else {
+            face
+        }
+    }
+
+    /// The face wound counter-clockwise when seen from the positive height
+    /// axis, so exported faces point up
+    fn exported_winding(&self, face: [VertexId; 3]) -> [VertexId; 3] {
+        if self.projection.is_mirrored() {
+            face
+        } else {
+            [face[0], face[2], face[1]]
+        }
+    }
+
+    /// Writes the triangulation as a glTF model, in binary form if the path
+    /// ends in `.glb`. A `.gltf` file references its buffer in a `.bin` file
+    /// of the same name.
+    ///
+    /// Like [`CDT::export_to_obj`], vertices are placed back in 3D with the
+    /// inverse of the projection they were imported with.
+    pub fn export_to_gltf(&self, model_path: &str) -> CadetResult<()> {
+        self.projection.check()?;
+
+        if self.faces.is_empty() {
+            return Err(CadetError::EmptyModel);
+        }
+
+        let mut gltf_indices = HashMap::new();
+        let mut positions = Vec::new();
+
+        for (id, vertex) in self.vertices.iter() {
+            gltf_indices.insert(id, gltf_indices.len() as u32);
+            let p = self.projection.unproject(vertex.position, vertex.height);
+            positions.push(p.as_vec3().to_array());
+        }
+
+        let indices = self
+            .faces
+            .values()
+            .flat_map(|face| {
+                self.exported_winding(face.vertex_indices())
+                    .map(|v| gltf_indices[&v])
             })
-            .map(|v| Rc::new(RefCell::new(v)))
             .collect::<Vec<_>>();
 
-        let indices = first_scene
-            .models
+        let mut bin = positions
             .iter()
-            .flat_map(|m| m.indices())
             .flatten()
+            .flat_map(|coordinate| coordinate.to_le_bytes())
             .collect::<Vec<_>>();
+        let positions_length = bin.len();
+        bin.extend(indices.iter().flat_map(|index| index.to_le_bytes()));
 
-        let mut cdt = CDT {
-            vertices: vertices.clone(),
-            ..Default::default()
+        let path = Path::new(model_path);
+        let is_binary = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("glb"));
+        let bin_path = path.with_extension("bin");
+
+        let uri = if is_binary {
+            None
+        } else {
+            bin_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
         };
 
-        indices.chunks(3).for_each(|c| {
-            let v = c.iter().map(|&x| *x as usize).collect::<Vec<_>>();
-            println!("Adding face {:?}", v);
-            cdt.add_face([
-                vertices[v[0]].clone(),
-                vertices[v[1]].clone(),
-                vertices[v[2]].clone(),
-            ]);
-        });
+        let root = gltf_root(&positions, indices.len(), positions_length, bin.len(), uri);
+        let json = root
+            .to_vec()
+            .map_err(|err| CadetError::Gltf(Box::new(err)))?;
+
+        if is_binary {
+            let glb = gltf::binary::Glb {
+                header: gltf::binary::Header {
+                    magic: *b"glTF",
+                    version: 2,
+                    // Computed again when writing
+                    length: 0,
+                },
+                json: Cow::Owned(json),
+                bin: Some(Cow::Owned(bin)),
+            };
+
+            let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            glb.to_writer(writer)
+                .map_err(|err| CadetError::Gltf(Box::new(err)))?;
+        } else {
+            std::fs::write(path, json)?;
+            std::fs::write(bin_path, bin)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses an OBJ file, with indices starting at zero
+pub(crate) fn read_obj(model_path: &str) -> CadetResult<obj::raw::RawObj> {
+    let file = std::fs::File::open(model_path)?;
+
+    obj::raw::parse_obj(std::io::BufReader::new(file)).map_err(CadetError::Obj)
+}
+
+/// Error for an OBJ element referencing a vertex the file does not define
+// The parser does not check the indices, and `LoadError::new` is the only way
+// to report it the same way as its own errors
+#[allow(deprecated)]
+pub(crate) fn missing_obj_vertex() -> CadetError {
+    CadetError::Obj(obj::ObjError::Load(obj::LoadError::new(
+        obj::LoadErrorKind::IndexOutOfRange,
+        "An element references a missing vertex",
+    )))
+}
+
+/// Position indices of the vertices of an OBJ polygon
+fn polygon_positions(polygon: &obj::raw::object::Polygon) -> Vec<usize> {
+    use obj::raw::object::Polygon;
+
+    match polygon {
+        Polygon::P(vertices) => vertices.clone(),
+        Polygon::PT(vertices) | Polygon::PN(vertices) => vertices.iter().map(|&(p, _)| p).collect(),
+        Polygon::PTN(vertices) => vertices.iter().map(|&(p, _, _)| p).collect(),
+    }
+}
+
+/// Vertex indices of the faces of a primitive. Primitives of points or
+/// lines have none.
+fn primitive_faces(model: &easy_gltf::Model) -> CadetResult<Vec<[usize; 3]>> {
+    let indices = match model.indices() {
+        Some(indices) => indices.iter().map(|&i| i as usize).collect::<Vec<_>>(),
+        None => (0..model.vertices().len()).collect(),
+    };
+
+    let faces = match model.mode() {
+        easy_gltf::model::Mode::Triangles => {
+            if indices.len() % 3 != 0 {
+                return Err(CadetError::TopologyCorrupt(format!(
+                    "A primitive has {} indices, which is not a whole number of triangles",
+                    indices.len()
+                )));
+            }
+
+            indices.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()
+        }
+        // Every other triangle of a strip is reversed to keep the winding
+        easy_gltf::model::Mode::TriangleStrip => indices
+            .windows(3)
+            .enumerate()
+            .map(|(i, w)| {
+                if i % 2 == 0 {
+                    [w[0], w[1], w[2]]
+                } else {
+                    [w[1], w[0], w[2]]
+                }
+            })
+            .collect(),
+        easy_gltf::model::Mode::TriangleFan => indices
+            .windows(2)
+            .skip(1)
+            .map(|w| [indices[0], w[0], w[1]])
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(faces)
+}
+
+/// glTF document with a single mesh, whose buffer holds the positions
+/// followed by the indices
+fn gltf_root(
+    positions: &[[f32; 3]],
+    index_count: usize,
+    positions_length: usize,
+    buffer_length: usize,
+    uri: Option<String>,
+) -> json::Root {
+    let mut root = json::Root::default();
+
+    let (min, max) = positions.iter().fold(
+        ([f32::MAX; 3], [f32::MIN; 3]),
+        |(mut min, mut max), position| {
+            for i in 0..3 {
+                min[i] = min[i].min(position[i]);
+                max[i] = max[i].max(position[i]);
+            }
+            (min, max)
+        },
+    );
+
+    let buffer = root.push(json::Buffer {
+        byte_length: USize64::from(buffer_length),
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        uri,
+    });
+
+    let mut push_view = |offset: usize, length: usize, target: json::buffer::Target| {
+        root.push(json::buffer::View {
+            buffer,
+            byte_length: USize64::from(length),
+            byte_offset: Some(USize64::from(offset)),
+            byte_stride: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            target: Some(Valid(target)),
+        })
+    };
+
+    let positions_view = push_view(0, positions_length, json::buffer::Target::ArrayBuffer);
+    let indices_view = push_view(
+        positions_length,
+        buffer_length - positions_length,
+        json::buffer::Target::ElementArrayBuffer,
+    );
+
+    let positions_accessor = root.push(json::Accessor {
+        buffer_view: Some(positions_view),
+        byte_offset: None,
+        count: USize64::from(positions.len()),
+        component_type: Valid(json::accessor::GenericComponentType(
+            json::accessor::ComponentType::F32,
+        )),
+        extensions: Default::default(),
+        extras: Default::default(),
+        type_: Valid(json::accessor::Type::Vec3),
+        min: Some(json::Value::from(Vec::from(min))),
+        max: Some(json::Value::from(Vec::from(max))),
+        name: None,
+        normalized: false,
+        sparse: None,
+    });
+
+    let indices_accessor = root.push(json::Accessor {
+        buffer_view: Some(indices_view),
+        byte_offset: None,
+        count: USize64::from(index_count),
+        component_type: Valid(json::accessor::GenericComponentType(
+            json::accessor::ComponentType::U32,
+        )),
+        extensions: Default::default(),
+        extras: Default::default(),
+        type_: Valid(json::accessor::Type::Scalar),
+        min: None,
+        max: None,
+        name: None,
+        normalized: false,
+        sparse: None,
+    });
+
+    let primitive = json::mesh::Primitive {
+        attributes: [(Valid(json::mesh::Semantic::Positions), positions_accessor)]
+            .into_iter()
+            .collect(),
+        extensions: Default::default(),
+        extras: Default::default(),
+        indices: Some(indices_accessor),
+        material: None,
+        mode: Valid(json::mesh::Mode::Triangles),
+        targets: None,
+    };
+
+    let mesh = root.push(json::Mesh {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        primitives: vec![primitive],
+        weights: None,
+    });
+
+    let node = root.push(json::Node {
+        mesh: Some(mesh),
+        ..Default::default()
+    });
+
+    let scene = root.push(json::Scene {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        nodes: vec![node],
+    });
+    root.scene = Some(scene);
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use glam::{DVec2, DVec3};
+
+    use crate::{error::CadetError, projection::Projection};
+
+    use super::{ImportOptions, CDT};
+
+    fn temp_path(name: &str, extension: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "cadet-{}-{}.{}",
+            name,
+            std::process::id(),
+            extension
+        ));
+        path.to_str().unwrap().to_string()
+    }
+
+    fn write_obj(name: &str, contents: &str) -> String {
+        let path = temp_path(name, "obj");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Rings of points around the origin whose heights slope along both
+    /// axes. The hull has no collinear points, which would leave slivers
+    /// that the single precision of the models can flip.
+    fn terrain() -> CDT {
+        let points = std::iter::once(DVec2::ZERO)
+            .chain((1..4).flat_map(|ring| {
+                (0..6 * ring).map(move |i| {
+                    let angle =
+                        std::f64::consts::TAU * (i as f64 + 0.5 * ring as f64) / (6 * ring) as f64;
+                    ring as f64 * DVec2::from_angle(angle)
+                })
+            }))
+            .collect::<Vec<_>>();
+        let mut cdt = CDT::from_points(&points).unwrap();
 
-        println!("Loaded model from {}", model_path);
+        for (_, vertex) in cdt.vertices.iter_mut() {
+            vertex.height = 0.5 * vertex.position.x - 0.25 * vertex.position.y;
+        }
 
         cdt
     }
 
-    pub fn export_to_obj(&self, model_path: &str) {
-        let file = std::fs::File::create(model_path).expect("Failed to create file");
-        let mut writer = std::io::BufWriter::new(file);
+    /// Checks that every vertex came back at its position and height, and
+    /// every face with the same vertices
+    fn assert_round_trip(exported: &CDT, imported: &CDT) {
+        assert_eq!(imported.vertices.len(), exported.vertices.len());
+        assert_eq!(imported.faces.len(), exported.faces.len());
+
+        // Models store single precision coordinates
+        let matches = exported
+            .vertices
+            .iter()
+            .map(|(id, vertex)| {
+                let (imported_id, imported_vertex) = imported
+                    .vertices
+                    .iter()
+                    .find(|(_, other)| other.position.distance(vertex.position) < 1e-5)
+                    .unwrap_or_else(|| panic!("{:?} was not imported", vertex.position));
 
-        for vertex in self.vertices.iter() {
-            let vertex = vertex.borrow();
-            writeln!(writer, "v {} {} 0.0", vertex.position.x, vertex.position.y)
-                .expect("Failed to write to file");
+                assert!((imported_vertex.height - vertex.height).abs() < 1e-5);
+
+                (id, imported_id)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let imported_faces = imported
+            .faces
+            .values()
+            .map(|face| face.vertices)
+            .collect::<HashSet<_>>();
+
+        for face in exported.faces.values() {
+            let [a, b, c] = face.vertices.map(|v| matches[&v]);
+            assert!(
+                [[a, b, c], [b, c, a], [c, a, b]]
+                    .iter()
+                    .any(|face| imported_faces.contains(face)),
+                "{:?} was not imported",
+                face.vertices
+            );
         }
 
-        for face in self.faces.iter() {
-            let face = face.borrow();
-            let indices = face.vertex_indices();
-            writeln!(
-                writer,
-                "f {} {} {}",
-                indices[0] + 1,
-                indices[1] + 1,
-                indices[2] + 1
-            )
-            .expect("Failed to write to file");
+        assert!(imported.validate().is_valid(), "{}", imported.validate());
+    }
+
+    #[test]
+    fn exported_glb_models_import_back() {
+        let cdt = terrain();
+        let path = temp_path("round-trip", "glb");
+
+        cdt.export_to_gltf(&path).unwrap();
+
+        assert_round_trip(&cdt, &CDT::from_gltf(&path).unwrap());
+    }
+
+    #[test]
+    fn exported_gltf_models_import_back_with_their_buffer() {
+        let cdt = terrain();
+        let path = temp_path("round-trip", "gltf");
+
+        cdt.export_to_gltf(&path).unwrap();
+        assert!(std::path::Path::new(&temp_path("round-trip", "bin")).exists());
+
+        assert_round_trip(&cdt, &CDT::from_gltf(&path).unwrap());
+    }
+
+    #[test]
+    fn exported_obj_models_import_back() {
+        let cdt = terrain();
+        let path = temp_path("round-trip", "obj");
+
+        cdt.export_to_obj(&path).unwrap();
+
+        assert_round_trip(&cdt, &CDT::from_obj(&path).unwrap());
+    }
+
+    #[test]
+    fn obj_polygons_are_triangulated() {
+        let path = write_obj("square", "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nf 1 2 3 4\n");
+
+        let cdt = CDT::from_obj(&path).unwrap();
+        assert_eq!(cdt.vertices.len(), 4);
+        assert_eq!(cdt.faces.len(), 2);
+    }
+
+    #[test]
+    fn degenerate_projection_planes_are_rejected() {
+        let path = write_obj("plane", "v 0 0 0\nv 1 0 0\nv 1 0 1\nf 1 2 3\n");
+        let options = ImportOptions {
+            projection: Projection::Plane {
+                origin: DVec3::ZERO,
+                u: DVec3::X,
+                v: 2. * DVec3::X,
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            CDT::from_obj_with_options(&path, &options),
+            Err(CadetError::DegenerateProjection)
+        ));
+
+        let mut cdt = CDT::from_obj(&path).unwrap();
+        cdt.projection = options.projection;
+        assert!(matches!(
+            cdt.export_to_obj(&temp_path("plane-export", "obj")),
+            Err(CadetError::DegenerateProjection)
+        ));
+    }
+
+    #[test]
+    fn obj_polygons_with_a_missing_vertex_are_rejected() {
+        let path = write_obj("missing", "v 0 0 0\nv 1 0 0\nv 1 0 1\nf 1 2 5\n");
+
+        assert!(matches!(CDT::from_obj(&path), Err(CadetError::Obj(_))));
+    }
+
+    fn assert_topology_corrupt(path: &str, expected: &str) {
+        match CDT::from_obj(path) {
+            Err(CadetError::TopologyCorrupt(message)) => {
+                assert!(message.contains(expected), "{}", message)
+            }
+            result => panic!("{:?} was not rejected", result.map(|cdt| cdt.faces.len())),
         }
+    }
 
-        writer.flush().expect("Failed to flush buffer");
+    #[test]
+    fn edges_of_more_than_two_faces_are_rejected() {
+        let path = write_obj(
+            "non-manifold",
+            "v 0 0 0\nv 1 0 0\nv 0.5 0 1\nv 0.5 0 -1\nv 0.5 0 2\nf 1 2 3\nf 1 2 4\nf 1 2 5\n",
+        );
 
-        // println!("Exported to {}", model_path);
+        assert_topology_corrupt(&path, "shared by more than two faces");
+    }
+
+    #[test]
+    fn faces_folded_over_a_shared_edge_are_rejected() {
+        let path = write_obj(
+            "folded",
+            "v 0 0 0\nv 1 0 0\nv 0.5 0 1\nv 0.5 1 2\nf 1 2 3\nf 2 1 4\n",
+        );
+
+        assert_topology_corrupt(&path, "Faces overlap along edge");
+    }
+
+    #[test]
+    fn faces_overlapping_around_a_vertex_are_rejected() {
+        let path = write_obj(
+            "overlapping",
+            "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 2 0 0.2\nv 2 0 3\nf 1 2 3\nf 1 4 5\n",
+        );
+
+        assert_topology_corrupt(&path, "Faces overlap around vertex");
+    }
+
+    #[test]
+    fn faces_without_area_in_the_plane_are_rejected() {
+        // A wall, seen edge-on from above
+        let path = write_obj("wall", "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n");
+
+        assert_topology_corrupt(&path, "has no area");
+    }
+
+    #[test]
+    fn bundled_models_import_as_valid_triangulations() {
+        let cdt = CDT::from_gltf("models/model.glb").unwrap();
+        assert_eq!(cdt.vertices.len(), 4);
+        assert_eq!(cdt.faces.len(), 2);
+        assert!(cdt.validate().is_valid(), "{}", cdt.validate());
+
+        // The OBJ model is Z-up, so it collapses onto the default XZ plane
+        assert!(matches!(
+            CDT::from_obj("models/output.obj"),
+            Err(CadetError::TopologyCorrupt(_))
+        ));
+
+        let options = ImportOptions {
+            projection: Projection::XY,
+            ..Default::default()
+        };
+        let cdt = CDT::from_obj_with_options("models/output.obj", &options).unwrap();
+        assert_eq!(cdt.vertices.len(), 46);
+        assert_eq!(cdt.faces.len(), 86);
+        assert!(cdt.validate().is_valid(), "{}", cdt.validate());
+    }
+
+    #[test]
+    fn bundled_models_folding_over_themselves_are_rejected() {
+        assert!(matches!(
+            CDT::from_gltf("models/random.glb"),
+            Err(CadetError::TopologyCorrupt(_))
+        ));
     }
 }
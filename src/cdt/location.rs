@@ -1,184 +1,213 @@
-use std::{cell::RefCell, rc::Rc};
-
-use geo::Coord;
 use glam::DVec2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::{face::Face, helper::is_ccw, locate_result::LocateResult, orientation::Orientation};
+use crate::{
+    face::{Face, FaceId},
+    helper::is_ccw,
+    locate_result::LocateResult,
+    orientation::Orientation,
+    predicates::in_triangle,
+    vertex::VertexId,
+};
 
 use super::cdt::CDT;
 
-impl CDT {
-    // pub fn locate_point(&self, p: &DVec2) -> LocateResult {
-    //     // Step 1: Jump - Select a random vertex sample and find the closest one
-    //     let num_vertices = self.faces.len();
-    //     let sample_size = (num_vertices as f64).powf(1.0 / 3.0).ceil() as usize;
-    //     let mut rng = rand::thread_rng();
-
-    //     let random_sample = self.faces.iter().choose_multiple(&mut rng, sample_size);
-
-    //     let mut closest_face = random_sample[0].clone();
-
-    //     let mut min_distance = f64::MAX;
-
-    //     for triangle in random_sample {
-    //         for vertex in &triangle.borrow().vertices {
-    //             let vertex = vertex.borrow();
-    //             let distance =
-    //                 (vertex.position.x - p.x).powi(2) + (vertex.position.y - p.y).powi(2);
-    //             if distance < min_distance {
-    //                 min_distance = distance;
-    //                 closest_face = triangle.clone();
-    //             }
-    //         }
-    //     }
-
-    //     // Step 2: Walk - Oriented walk to locate p
-    //     let mut visited = HashMap::<usize, bool>::new();
-
-    //     loop {
-    //         assert!(self.faces.iter().any(|f| Rc::ptr_eq(&f, &closest_face)));
-    //         let vertices = closest_face.borrow().vertices.clone();
-    //         let vertices = [
-    //             vertices[0].borrow(),
-    //             vertices[1].borrow(),
-    //             vertices[2].borrow(),
-    //         ];
-
-    //         let centroid = DVec2 {
-    //             x: (vertices[0].position.x + vertices[1].position.x + vertices[2].position.x) / 3.0,
-    //             y: (vertices[0].position.y + vertices[1].position.y + vertices[2].position.y) / 3.0,
-    //         };
-
-    //         let mut selected_edge_index = None;
-
-    //         // Find the edge that separates p and the centroid
-    //         for (i, edge) in closest_face.borrow().edges.iter().enumerate() {
-    //             let sym_edge = self.get_sym_edge_for_half_edge(edge).unwrap();
-    //             let edge_borrowed = sym_edge.borrow();
-    //             let a = edge_borrowed.a();
-    //             let b = edge_borrowed.b();
-    //             let vertex = a.borrow();
-    //             let next_vertex = b.borrow();
-
-    //             let is_point_ccw = Self::is_ccw(&vertex.position, &next_vertex.position, p);
-    //             let is_centroid_ccw =
-    //                 Self::is_ccw(&vertex.position, &next_vertex.position, &centroid);
-    //             let is_separating_edge = is_point_ccw != is_centroid_ccw;
-
-    //             if is_point_ccw == Orientation::Collinear {
-    //                 return LocateResult::Edge(edge_borrowed.edge.clone());
-    //             }
-
-    //             if is_separating_edge {
-    //                 selected_edge_index = Some(i);
-    //                 break;
-    //             }
-    //         }
-
-    //         if let Some(edge_index) = selected_edge_index {
-    //             // Move to the adjacent triangle across the selected edge
-    //             if let Some(neighbor) =
-    //                 self.find_neighboring_face(&closest_face.clone().borrow(), edge_index)
-    //             {
-    //                 if visited.get(&neighbor.borrow().id).copied().unwrap_or(false) {
-    //                     // A loop is detected; fallback to epsilon-based checks
-    //                     if Self::is_point_on_edge(p, &closest_face.borrow()) {
-    //                         let sym_edge = self.get_sym_edge_for_half_edge(
-    //                             &closest_face.borrow().edges[edge_index],
-    //                         );
-    //                         let sym_edge = sym_edge.unwrap();
-    //                         let edge = sym_edge.borrow().edge.clone();
-    //                         return LocateResult::Edge(edge);
-    //                     }
-    //                     return LocateResult::Face(closest_face.clone());
-    //                 }
-
-    //                 visited.insert(closest_face.borrow().id, true);
-    //                 closest_face = neighbor.clone();
-    //             } else {
-    //                 // Check if point lies outside the convex hull
-    //                 let edge = &closest_face.borrow().edges[edge_index];
-    //                 let edge = self.get_sym_edge_for_half_edge(edge).unwrap();
-    //                 let edge_borrowed = edge.borrow();
-    //                 let is_ccw = Self::is_ccw(
-    //                     &edge_borrowed.a().borrow().position,
-    //                     &edge_borrowed.b().borrow().position,
-    //                     p,
-    //                 ) == Orientation::CounterClockwise;
-    //                 if is_ccw {
-    //                     return LocateResult::None;
-    //                 }
-    //                 return LocateResult::Face(closest_face.clone());
-    //             }
-    //         } else {
-    //             // The point is inside the current triangle
-    //             return LocateResult::Face(closest_face.clone());
-    //         }
-    //     }
-    // }
+/// Where a walk towards a point ends
+enum WalkEnd {
+    /// In the face containing the point
+    Inside(FaceId),
+    /// At a boundary edge of the face, with the point outside of the domain
+    Outside(FaceId),
+    /// Nowhere, as the walk did not terminate
+    Lost,
+}
 
+impl CDT {
+    /// Locates `p` with a jump-and-walk.
+    ///
+    /// The walk starts from the closest of the face found by the previous
+    /// call, if it still exists, and a small sample of faces. It then moves
+    /// across the edges that separate the current face from `p`. The sample
+    /// and the order the edges are tried in are drawn from a generator seeded
+    /// with `p`, so the same query on the same mesh takes the same walk.
+    ///
+    /// A walk that crosses a boundary edge ends there, with `p` outside of
+    /// the domain. Only a walk that does not terminate, which can happen in
+    /// triangulations that are not Delaunay, falls back to a scan over all
+    /// faces.
     pub fn locate_point(&self, p: &DVec2) -> LocateResult {
-        //Brute force method
-        for face in &self.faces {
-            let vertices = face.borrow().vertices.clone();
-            let vertices = [
-                vertices[0].borrow(),
-                vertices[1].borrow(),
-                vertices[2].borrow(),
-            ];
-
-            let tri = geo::Triangle::new(
-                Coord {
-                    x: vertices[0].position.x,
-                    y: vertices[0].position.y,
-                },
-                Coord {
-                    x: vertices[1].position.x,
-                    y: vertices[1].position.y,
-                },
-                Coord {
-                    x: vertices[2].position.x,
-                    y: vertices[2].position.y,
-                },
-            );
-
-            let is_point_in_triangle = tri.locate_point(p);
-            if is_point_in_triangle {
-                for edge in &face.borrow().edge_indices() {
-                    let sym_edge = self.get_sym_edge_for_half_edge(edge).unwrap();
-                    let edge = sym_edge.borrow().edge.clone();
-
-                    let a = edge.borrow().a.clone();
-                    let b = edge.borrow().b.clone();
-
-                    let pa = a.borrow().position.distance(*p);
-                    let pb = b.borrow().position.distance(*p);
-                    let ab = a.borrow().position.distance(b.borrow().position);
-
-                    if pa + pb - ab < 1e-6 {
-                        if pa < 0.0001 {
-                            return LocateResult::Vertex(a);
-                        }
-
-                        if pb < 0.0001 {
-                            return LocateResult::Vertex(b);
-                        }
-                        return LocateResult::Edge(edge);
-                    }
-                }
-                return LocateResult::Face(face.clone());
+        let mut rng = StdRng::seed_from_u64(p.x.to_bits() ^ p.y.to_bits().rotate_left(32));
+
+        let start = match self.jump(p, &mut rng) {
+            Some(face) => face,
+            None => return LocateResult::None,
+        };
+
+        match self.walk(start, p, &mut rng) {
+            WalkEnd::Inside(face) => {
+                self.last_located_face.set(Some(face));
+                self.classify_point_in_face(face, p)
             }
+            WalkEnd::Outside(face) => {
+                self.last_located_face.set(Some(face));
+                LocateResult::None
+            }
+            WalkEnd::Lost => self.locate_point_brute_force(p),
         }
+    }
 
-        LocateResult::None
+    /// A face containing `p`, one of several if it lies on an edge or vertex
+    pub(crate) fn locate_face(&self, p: &DVec2) -> Option<FaceId> {
+        let sym_edge = match self.locate_point(p) {
+            LocateResult::Face(face) => return Some(face),
+            LocateResult::Edge(edge) => *self.get_all_sym_edges_for_edge(edge).first()?,
+            LocateResult::Vertex(vertex) => *self.sym_edges_by_vertices.get(&vertex)?.first()?,
+            LocateResult::None => return None,
+        };
+
+        Some(self.sym_edges[sym_edge].face)
+    }
+
+    fn last_located_face(&self) -> Option<FaceId> {
+        // The face may have been removed since the last call
+        self.last_located_face
+            .get()
+            .filter(|&face| self.faces.contains(face))
+    }
+
+    /// The face closest to `p` out of the face the last walk ended in and a
+    /// sample of about the cube root of the number of faces, drawn by index
+    /// in time proportional to its size
+    fn jump(&self, p: &DVec2, rng: &mut StdRng) -> Option<FaceId> {
+        let sample_size = (self.faces.len() as f64).powf(1.0 / 3.0).ceil() as usize;
+
+        let sample = (0..sample_size).filter_map(|_| self.faces.random_id(rng));
+
+        self.last_located_face()
+            .into_iter()
+            .chain(sample)
+            .map(|id| {
+                let distance = self.faces[id]
+                    .vertices
+                    .iter()
+                    .map(|&v| self.vertices[v].position.distance_squared(*p))
+                    .fold(f64::MAX, f64::min);
+
+                (id, distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
+    /// Starts the next walk from a face around `vertex`, as inserting it
+    /// removed the face the last walk ended in
+    pub(crate) fn start_walks_at(&self, vertex: VertexId) {
+        let face = self
+            .sym_edges_by_vertices
+            .get(&vertex)
+            .and_then(|sym_edges| sym_edges.first())
+            .map(|&sym_edge| self.sym_edges[sym_edge].face);
+
+        self.last_located_face.set(face);
+    }
+
+    /// Visibility walk towards `p`.
+    ///
+    /// The edge to cross is picked starting from a random offset, which keeps
+    /// the walk from cycling in non-Delaunay triangulations.
+    fn walk(&self, start: FaceId, p: &DVec2, rng: &mut StdRng) -> WalkEnd {
+        let mut face = start;
+
+        for _ in 0..=self.faces.len() {
+            let edges = self.faces[face].edge_indices();
+            let offset = rng.gen_range(0..3);
+
+            let separating_edge = (0..3)
+                .map(|i| edges[(i + offset) % 3])
+                .find(|edge| self.is_separating_edge(&self.faces[face], edge, p));
+
+            let edge = match separating_edge {
+                Some(edge) => edge,
+                None => return WalkEnd::Inside(face),
+            };
+
+            let neighbor = self
+                .get_sym_edge_for_half_edge(&edge)
+                .and_then(|sym_edge| self.neighbor_face(sym_edge));
+
+            face = match neighbor {
+                Some(neighbor) => neighbor,
+                None => return WalkEnd::Outside(face),
+            };
+        }
+
+        WalkEnd::Lost
+    }
+
+    /// Whether `p` lies strictly on the other side of `edge` than the face
+    fn is_separating_edge(&self, face: &Face, edge: &(VertexId, VertexId), p: &DVec2) -> bool {
+        let a = self.vertices[edge.0].position;
+        let b = self.vertices[edge.1].position;
+
+        let opposite = face
+            .vertices
+            .iter()
+            .find(|&&v| v != edge.0 && v != edge.1)
+            .expect("Edge not found in face");
+        let c = self.vertices[*opposite].position;
+
+        let point_orientation = is_ccw(&a, &b, p);
+
+        point_orientation != Orientation::Collinear && point_orientation != is_ccw(&a, &b, &c)
+    }
+
+    /// Location of `p` in `face`, or `None` if it lies outside of the face
+    pub(crate) fn locate_in_face(&self, face: FaceId, p: &DVec2) -> Option<LocateResult> {
+        let face_data = &self.faces[face];
+
+        face_data
+            .edge_indices()
+            .iter()
+            .all(|edge| !self.is_separating_edge(face_data, edge, p))
+            .then(|| self.classify_point_in_face(face, p))
     }
 
-    pub fn is_point_on_edge(p: &DVec2, triangle: &Face) -> bool {
-        for i in 0..3 {
-            let a = triangle.vertices[i].borrow();
-            let b = triangle.vertices[(i + 1) % 3].borrow();
+    /// Classifies a point known to be inside or on the boundary of `face`
+    fn classify_point_in_face(&self, face: FaceId, p: &DVec2) -> LocateResult {
+        let face_data = &self.faces[face];
 
-            let is_ccw = is_ccw(&a.position, &b.position, p);
+        for &vertex in face_data.vertices.iter() {
+            if self.vertices[vertex].position == *p {
+                return LocateResult::Vertex(vertex);
+            }
+        }
+
+        for edge in face_data.edge_indices().iter() {
+            let a = self.vertices[edge.0].position;
+            let b = self.vertices[edge.1].position;
+
+            if is_ccw(&a, &b, p) == Orientation::Collinear {
+                return LocateResult::Edge(self.find_edge(edge.0, edge.1).unwrap());
+            }
+        }
+
+        LocateResult::Face(face)
+    }
+
+    fn locate_point_brute_force(&self, p: &DVec2) -> LocateResult {
+        for id in self.faces.ids() {
+            if let Some(result) = self.locate_in_face(id, p) {
+                self.last_located_face.set(Some(id));
+                return result;
+            }
+        }
+
+        LocateResult::None
+    }
+
+    pub fn is_point_on_edge(&self, p: &DVec2, triangle: &Face) -> bool {
+        for (a, b) in triangle.edge_indices() {
+            let is_ccw = is_ccw(&self.vertices[a].position, &self.vertices[b].position, p);
             if is_ccw == Orientation::Collinear {
                 return true;
             }
@@ -186,17 +215,12 @@ impl CDT {
         false
     }
 
-    pub fn find_neighboring_face(
-        &self,
-        face: &Face,
-        edge_index: usize,
-    ) -> Option<Rc<RefCell<Face>>> {
+    pub fn find_neighboring_face(&self, face: &Face, edge_index: usize) -> Option<FaceId> {
         // Get the SymEdge corresponding to the edge
         let edge = &face.edge_indices()[edge_index];
-        let binding = self.get_sym_edge_for_half_edge(edge)?;
-        let sym_edge = binding.borrow();
+        let sym_edge = self.get_sym_edge_for_half_edge(edge)?;
 
-        sym_edge.neighbor_face()
+        self.neighbor_face(sym_edge)
     }
 }
 
@@ -209,24 +233,83 @@ where
     T: geo::GeoNum,
 {
     fn locate_point(&self, coord: &DVec2) -> bool {
-        let p0x = self.0.x.to_f64().unwrap();
-        let p0y = self.0.y.to_f64().unwrap();
-        let p1x = self.1.x.to_f64().unwrap();
-        let p1y = self.1.y.to_f64().unwrap();
-        let p2x = self.2.x.to_f64().unwrap();
-        let p2y = self.2.y.to_f64().unwrap();
+        let to_dvec2 = |c: geo::Coord<T>| DVec2::new(c.x.to_f64().unwrap(), c.y.to_f64().unwrap());
+
+        in_triangle(to_dvec2(self.0), to_dvec2(self.1), to_dvec2(self.2), *coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::locate_result::LocateResult;
+
+    use super::{WalkEnd, CDT};
 
-        let px = coord.x;
-        let py = coord.y;
+    #[test]
+    fn jump_and_walk_matches_the_brute_force_scan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut random_point = || DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
 
-        let a = 0.5 * (-p1y * p2x + p0y * (-p1x + p2x) + p0x * (p1y - p2y) + p1x * p2y);
+        let points = (0..500).map(|_| random_point()).collect::<Vec<_>>();
+        let cdt = CDT::from_points(&points).unwrap();
 
-        let sign = a.signum();
-        let epsilon = 1e-9;
+        // Vertices and points on or next to edges as well
+        let midpoints = cdt
+            .edges
+            .values()
+            .map(|edge| (cdt.vertices[edge.a].position + cdt.vertices[edge.b].position) / 2.);
+        let queries = (0..500)
+            .map(|_| random_point())
+            .chain(points.iter().copied())
+            .chain(midpoints)
+            .collect::<Vec<_>>();
 
-        let s = (p0y * p2x - p0x * p2y + (p2y - p0y) * px + (p0x - p2x) * py) * sign;
-        let t = (p0x * p1y - p0y * p1x + (p0y - p1y) * px + (p1x - p0x) * py) * sign;
+        for p in queries {
+            assert_eq!(cdt.locate_point(&p), cdt.locate_point_brute_force(&p));
+        }
+    }
+
+    #[test]
+    fn walks_stop_at_the_boundary() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points = (0..200)
+            .map(|_| DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect::<Vec<_>>();
+        let cdt = CDT::from_points(&points).unwrap();
+
+        for face in cdt.faces.ids() {
+            let p = DVec2::new(rng.gen_range(-3.0..3.0), 2.);
+
+            assert!(matches!(cdt.walk(face, &p, &mut rng), WalkEnd::Outside(_)));
+            assert_eq!(cdt.locate_point(&p), LocateResult::None);
+        }
+    }
 
-        s > -epsilon && t > -epsilon && (s + t) < 2. * a * sign + epsilon
+    #[test]
+    fn queries_take_the_same_walk_every_time() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points = (0..300)
+            .map(|_| DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect::<Vec<_>>();
+
+        // Vertices lie in several faces, and the walk decides which one is found
+        let walks = (0..2)
+            .map(|_| {
+                let cdt = CDT::from_points(&points).unwrap();
+
+                points
+                    .iter()
+                    .map(|p| {
+                        cdt.locate_point(p);
+                        cdt.last_located_face.get()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(walks[0], walks[1]);
     }
 }
@@ -1,5 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod cdt;
 pub mod delanuay;
 pub mod io;
 pub mod location;
+pub mod refinement;
+pub mod regions;
+pub mod relocation;
+pub mod removal;
 pub mod structure;
+pub mod svg;
+pub mod validation;
@@ -0,0 +1,845 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use glam::DVec2;
+
+use crate::{
+    edge::EdgeId,
+    error::{CadetError, CadetResult},
+    face::FaceId,
+    helper::circumcenter,
+    locate_result::LocateResult,
+    predicates::{in_circumcircle, orient2d},
+    vertex::VertexId,
+};
+
+use super::cdt::CDT;
+
+/// Largest allowed area of a face as a function of its centroid, which has
+/// to be positive
+pub type SizingFunction = Arc<dyn Fn(DVec2) -> f64 + Send + Sync>;
+
+/// Area bound for the region around a point, which extends up to the
+/// constrained edges enclosing it
+#[derive(Clone, Copy, Debug)]
+pub struct AreaSeed {
+    pub point: DVec2,
+    pub max_area: f64,
+}
+
+/// Quality bounds for [`CDT::refine`]
+///
+/// The area bounds combine, each face has to meet the smallest one that
+/// applies to it.
+#[derive(Clone)]
+pub struct RefinementOptions {
+    /// Smallest allowed angle of a face, in degrees. Refinement is only
+    /// guaranteed to terminate for bounds up to about 20.7 degrees.
+    pub min_angle: f64,
+    /// Largest allowed area of a face
+    pub max_area: Option<f64>,
+    /// Largest allowed area of a face depending on where it lies
+    pub sizing: Option<SizingFunction>,
+    /// Largest allowed area of the faces in regions around seed points.
+    /// Where regions of several seeds overlap, the smallest bound applies.
+    pub area_seeds: Vec<AreaSeed>,
+    /// Edges shorter than this are not split any further, which stops the
+    /// refinement around small angles between constraints. Defaults to a
+    /// millionth of the size of the domain.
+    pub min_edge_length: Option<f64>,
+    /// Number of Steiner points after which the refinement stops
+    pub max_steiner_points: usize,
+}
+
+impl Default for RefinementOptions {
+    fn default() -> Self {
+        Self {
+            min_angle: 20.,
+            max_area: None,
+            sizing: None,
+            area_seeds: Vec::new(),
+            min_edge_length: None,
+            max_steiner_points: 100_000,
+        }
+    }
+}
+
+/// Outcome of [`CDT::refine`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RefinementReport {
+    /// Number of inserted Steiner points
+    pub inserted: usize,
+    /// Whether the refinement stopped at `max_steiner_points` with faces or
+    /// segments left to check, which may not meet the bounds
+    pub truncated: bool,
+}
+
+impl std::fmt::Debug for RefinementOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefinementOptions")
+            .field("min_angle", &self.min_angle)
+            .field("max_area", &self.max_area)
+            .field("sizing", &self.sizing.as_ref().map(|_| "Fn(DVec2) -> f64"))
+            .field("area_seeds", &self.area_seeds)
+            .field("min_edge_length", &self.min_edge_length)
+            .field("max_steiner_points", &self.max_steiner_points)
+            .finish()
+    }
+}
+
+/// Regions on either side of the line through `a` and `b`, taken before a
+/// vertex is inserted on the line or in a face
+struct RegionSides {
+    a: DVec2,
+    b: DVec2,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// Work left for [`CDT::refine`]
+#[derive(Default)]
+struct RefinementQueue {
+    segments: VecDeque<(VertexId, VertexId)>,
+    faces: VecDeque<[VertexId; 3]>,
+    /// Region of every face, if there are seeds. Segments are only split, so
+    /// the regions stay the same while their faces are replaced.
+    face_regions: HashMap<FaceId, usize>,
+    /// Area bound of every region from the seeds inside it
+    region_max_areas: Vec<Option<f64>>,
+}
+
+impl RefinementQueue {
+    fn region_max_area(&self, face: FaceId) -> Option<f64> {
+        self.face_regions
+            .get(&face)
+            .and_then(|&region| self.region_max_areas[region])
+    }
+}
+
+impl CDT {
+    /// Inserts Steiner points until every face meets the quality bounds, and
+    /// reports how many were inserted.
+    ///
+    /// This is Ruppert's algorithm: segments, which are the constrained edges
+    /// and the boundary of the domain, are split at their midpoint while a
+    /// vertex lies inside their diametral circle. Faces with a too small angle
+    /// or a too large area are split at their circumcenter, unless it would
+    /// encroach a segment, which is then split instead. Faces whose small
+    /// angle lies between two segments cannot be improved and are left as
+    /// they are.
+    ///
+    /// Fails if an area bound is not positive, or if a circumcenter lands on
+    /// a vertex or outside of the faces around it, which only happens when
+    /// the triangulation was not constrained Delaunay to begin with. The
+    /// triangulation is left unchanged then.
+    pub fn refine(&mut self, options: &RefinementOptions) -> CadetResult<RefinementReport> {
+        let area_bounds = options
+            .max_area
+            .into_iter()
+            .chain(options.area_seeds.iter().map(|seed| seed.max_area));
+        for max_area in area_bounds {
+            check_area_bound(max_area)?;
+        }
+
+        // Refine a copy, so that a failure does not leave a partly refined
+        // triangulation behind
+        let mut refined = self.clone();
+        let report = refined.refine_in_place(options)?;
+        *self = refined;
+
+        Ok(report)
+    }
+
+    fn refine_in_place(&mut self, options: &RefinementOptions) -> CadetResult<RefinementReport> {
+        let min_edge_length = options
+            .min_edge_length
+            .unwrap_or_else(|| self.domain_size() * 1e-6);
+
+        let (face_regions, region_max_areas) = self.seed_regions(&options.area_seeds);
+
+        let mut queue = RefinementQueue {
+            segments: self
+                .edges
+                .ids()
+                .filter(|&edge| self.is_segment(edge))
+                .map(|edge| self.edges[edge].edge_indices())
+                .collect(),
+            faces: self.faces.values().map(|face| face.vertices).collect(),
+            face_regions,
+            region_max_areas,
+        };
+
+        let mut inserted = 0;
+
+        while inserted < options.max_steiner_points {
+            if let Some((a, b)) = queue.segments.pop_front() {
+                if let Some(edge) = self.find_edge(a, b) {
+                    if self.is_segment(edge)
+                        && self.is_encroached(edge)
+                        && self.edge_length(edge) >= 2. * min_edge_length
+                    {
+                        self.split_segment(edge, &mut queue);
+                        inserted += 1;
+                    }
+                }
+                continue;
+            }
+
+            let vertices = match queue.faces.pop_front() {
+                Some(vertices) => vertices,
+                None => break,
+            };
+
+            let face = match self.find_face(vertices) {
+                Some(face) => face,
+                None => continue,
+            };
+
+            let sizing = match &options.sizing {
+                Some(sizing) => Some(check_area_bound(sizing(self.centroid(face)))?),
+                None => None,
+            };
+
+            let max_area = [options.max_area, sizing, queue.region_max_area(face)]
+                .into_iter()
+                .flatten()
+                .reduce(f64::min);
+
+            if !self.is_bad_face(vertices, options.min_angle, max_area, min_edge_length) {
+                continue;
+            }
+
+            let [a, b, c] = vertices.map(|v| self.vertices[v].position);
+            let center = circumcenter(&a, &b, &c);
+
+            // Slivers at constraint crossings can be collinear in floating point
+            if !center.is_finite() {
+                continue;
+            }
+
+            // The circumcenter would only be connected to the vertices of
+            // the faces whose circumcircle contains it, so only the segments
+            // around them can be encroached
+            let (cavity, segments) = self.circumcenter_cavity(face, center);
+
+            let encroached = segments
+                .into_iter()
+                .filter(|&(edge, inside)| {
+                    let edge = &self.edges[edge];
+                    let a = self.vertices[edge.a].position;
+                    let b = self.vertices[edge.b].position;
+
+                    is_in_diametral_circle(a, b, center)
+                        || is_beyond(a, b, self.vertices[inside].position, center)
+                })
+                .map(|(edge, _)| edge)
+                .collect::<Vec<_>>();
+
+            if !encroached.is_empty() {
+                let splittable = encroached
+                    .into_iter()
+                    .filter(|&edge| self.edge_length(edge) >= 2. * min_edge_length)
+                    .map(|edge| self.edges[edge].edge_indices())
+                    .collect::<Vec<_>>();
+
+                // The face is split again once the segments are, unless they
+                // are already too short
+                if !splittable.is_empty() {
+                    for (a, b) in splittable {
+                        if let Some(edge) = self.find_edge(a, b) {
+                            self.split_segment(edge, &mut queue);
+                            inserted += 1;
+                        }
+                    }
+
+                    queue.faces.push_back(vertices);
+                }
+                continue;
+            }
+
+            let location = cavity
+                .iter()
+                .find_map(|&face| self.locate_in_face(face, &center));
+
+            let (v, sides) = match location {
+                Some(LocateResult::Face(face)) => {
+                    let sides = self.face_region_sides(face, &queue);
+                    (self.insert_point_in_face(center, face), sides)
+                }
+                Some(LocateResult::Edge(edge)) => {
+                    let sides = self.edge_region_sides(edge, &queue);
+                    (self.insert_point_on_edge(center, edge), sides)
+                }
+                Some(LocateResult::Vertex(vertex)) => {
+                    return Err(CadetError::PositionOccupied {
+                        vertex,
+                        point: center,
+                    })
+                }
+                Some(LocateResult::None) | None => {
+                    return Err(CadetError::PointOutsideDomain(center))
+                }
+            };
+            self.vertices[v].steiner = true;
+
+            self.queue_vertex_star(v, sides, &mut queue);
+            inserted += 1;
+        }
+
+        Ok(RefinementReport {
+            inserted,
+            truncated: !queue.segments.is_empty() || !queue.faces.is_empty(),
+        })
+    }
+
+    /// Faces whose circumcircle contains `center`, found from `face` without
+    /// crossing segments, along with the segments bounding them. Every
+    /// segment comes with the vertex of the cavity face opposite to it.
+    fn circumcenter_cavity(
+        &self,
+        face: FaceId,
+        center: DVec2,
+    ) -> (Vec<FaceId>, Vec<(EdgeId, VertexId)>) {
+        let mut cavity = vec![face];
+        let mut visited = HashSet::from([face]);
+        let mut segments = Vec::new();
+        let mut i = 0;
+
+        while let Some(&face) = cavity.get(i) {
+            i += 1;
+
+            for (a, b) in self.faces[face].edge_indices() {
+                let edge = self.find_edge(a, b).unwrap();
+
+                if self.is_segment(edge) {
+                    segments.push((edge, self.faces[face].opposite_vertex(&self.edges[edge])));
+                    continue;
+                }
+
+                let Some(neighbor) = self.get_sym_edge_for_half_edge(&(b, a)) else {
+                    continue;
+                };
+                let neighbor = self.sym_edges[neighbor].face;
+                let [p, q, r] = self.faces[neighbor]
+                    .vertices
+                    .map(|v| self.vertices[v].position);
+
+                if in_circumcircle(p, q, r, center) && visited.insert(neighbor) {
+                    cavity.push(neighbor);
+                }
+            }
+        }
+
+        (cavity, segments)
+    }
+
+    fn split_segment(&mut self, edge: EdgeId, queue: &mut RefinementQueue) {
+        let (a, b) = self.edges[edge].edge_indices();
+        let midpoint = (self.vertices[a].position + self.vertices[b].position) / 2.;
+
+        let sides = self.edge_region_sides(edge, queue);
+        let v = self.insert_point_on_edge(midpoint, edge);
+        self.vertices[v].steiner = true;
+
+        self.queue_vertex_star(v, sides, queue);
+    }
+
+    fn face_region_sides(&self, face: FaceId, queue: &RefinementQueue) -> RegionSides {
+        let [a, b, _] = self.faces[face].vertices;
+        let region = queue.face_regions.get(&face).copied();
+
+        RegionSides {
+            a: self.vertices[a].position,
+            b: self.vertices[b].position,
+            left: region,
+            right: region,
+        }
+    }
+
+    fn edge_region_sides(&self, edge: EdgeId, queue: &RefinementQueue) -> RegionSides {
+        let edge_data = &self.edges[edge];
+        let a = self.vertices[edge_data.a].position;
+        let b = self.vertices[edge_data.b].position;
+
+        let mut sides = RegionSides {
+            a,
+            b,
+            left: None,
+            right: None,
+        };
+
+        for sym_edge in self.get_all_sym_edges_for_edge(edge) {
+            let face = self.sym_edges[sym_edge].face;
+            let o = self.vertices[self.faces[face].opposite_vertex(edge_data)].position;
+            let region = queue.face_regions.get(&face).copied();
+
+            if orient2d(a, b, o) > 0. {
+                sides.left = region;
+            } else {
+                sides.right = region;
+            }
+        }
+
+        sides
+    }
+
+    /// Region of every face, bounded by constrained edges, and the area bound
+    /// of every region from the seeds inside it. Both are empty if there are
+    /// no seeds.
+    fn seed_regions(&self, seeds: &[AreaSeed]) -> (HashMap<FaceId, usize>, Vec<Option<f64>>) {
+        if seeds.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
+        let mut face_regions = HashMap::new();
+        let mut region_count = 0;
+
+        for face in self.faces.ids() {
+            if face_regions.contains_key(&face) {
+                continue;
+            }
+
+            for region_face in self.flood_fill(face) {
+                face_regions.insert(region_face, region_count);
+            }
+            region_count += 1;
+        }
+
+        let mut region_max_areas = vec![None; region_count];
+
+        for seed in seeds {
+            let region = self
+                .locate_face(&seed.point)
+                .and_then(|face| face_regions.get(&face));
+
+            if let Some(&region) = region {
+                let max_area: &mut Option<f64> = &mut region_max_areas[region];
+                *max_area = Some(max_area.map_or(seed.max_area, |area| area.min(seed.max_area)));
+            }
+        }
+
+        (face_regions, region_max_areas)
+    }
+
+    /// Length of the diagonal of the bounding box of the vertices
+    fn domain_size(&self) -> f64 {
+        let (min, max) = self.vertices.values().fold(
+            (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+            |(min, max), vertex| (min.min(vertex.position), max.max(vertex.position)),
+        );
+
+        min.distance(max)
+    }
+
+    fn edge_length(&self, edge: EdgeId) -> f64 {
+        let edge = &self.edges[edge];
+        self.vertices[edge.a]
+            .position
+            .distance(self.vertices[edge.b].position)
+    }
+
+    pub(crate) fn centroid(&self, face: FaceId) -> DVec2 {
+        self.faces[face]
+            .vertices
+            .iter()
+            .map(|&v| self.vertices[v].position)
+            .sum::<DVec2>()
+            / 3.
+    }
+
+    /// Constrained edges and edges on the boundary of the domain
+    fn is_segment(&self, edge: EdgeId) -> bool {
+        self.edges[edge].is_constrained() || self.get_all_sym_edges_for_edge(edge).len() == 1
+    }
+
+    /// Whether a vertex opposite to the edge lies inside its diametral
+    /// circle. In a constrained Delaunay triangulation, a segment encroached
+    /// by any vertex is also encroached by one of these.
+    fn is_encroached(&self, edge: EdgeId) -> bool {
+        let edge_data = &self.edges[edge];
+        let a = self.vertices[edge_data.a].position;
+        let b = self.vertices[edge_data.b].position;
+
+        self.get_all_sym_edges_for_edge(edge)
+            .into_iter()
+            .map(|sym_edge| self.faces[self.sym_edges[sym_edge].face].opposite_vertex(edge_data))
+            .any(|v| is_in_diametral_circle(a, b, self.vertices[v].position))
+    }
+
+    /// The face with the given vertices, if it still exists
+    fn find_face(&self, [a, b, c]: [VertexId; 3]) -> Option<FaceId> {
+        let face = self.sym_edges[self.get_sym_edge_for_half_edge(&(a, b))?].face;
+
+        self.faces[face].contains_vertex(c).then_some(face)
+    }
+
+    /// Whether the face has an angle below the bound that does not lie
+    /// between two segments, or is larger than the area bound. Faces with an
+    /// edge below the minimum length are never bad.
+    fn is_bad_face(
+        &self,
+        vertices: [VertexId; 3],
+        min_angle: f64,
+        max_area: Option<f64>,
+        min_edge_length: f64,
+    ) -> bool {
+        let [a, b, c] = vertices.map(|v| self.vertices[v].position);
+
+        if [a.distance(b), b.distance(c), c.distance(a)]
+            .iter()
+            .any(|&length| length < min_edge_length)
+        {
+            return false;
+        }
+
+        if let Some(max_area) = max_area {
+            if (b - a).perp_dot(c - a).abs() / 2. > max_area {
+                return true;
+            }
+        }
+
+        // The smallest angle lies opposite to the shortest edge
+        let (i, shortest) = [(b, c), (c, a), (a, b)]
+            .iter()
+            .map(|(p, q)| p.distance(*q))
+            .enumerate()
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap();
+
+        let circumradius = circumcenter(&a, &b, &c).distance(a);
+        let face_angle = (shortest / (2. * circumradius)).clamp(-1., 1.).asin();
+
+        if face_angle.to_degrees() >= min_angle {
+            return false;
+        }
+
+        let apex = vertices[i];
+        let is_input_angle = [vertices[(i + 1) % 3], vertices[(i + 2) % 3]]
+            .iter()
+            .all(|&v| {
+                self.find_edge(apex, v)
+                    .is_some_and(|edge| self.is_segment(edge))
+            });
+
+        !is_input_angle
+    }
+
+    /// Queues the faces around a new vertex, and the segments among their
+    /// edges, which the vertex may encroach.
+    ///
+    /// The insertion only flips unconstrained edges, so every new face lies
+    /// in the region on its side of the line the vertex was inserted on.
+    fn queue_vertex_star(&self, v: VertexId, sides: RegionSides, queue: &mut RefinementQueue) {
+        let faces = self
+            .sym_edges_by_vertices
+            .get(&v)
+            .into_iter()
+            .flatten()
+            .map(|&sym_edge| self.sym_edges[sym_edge].face)
+            .collect::<Vec<_>>();
+
+        for face in faces {
+            for (a, b) in self.faces[face].edge_indices() {
+                if self
+                    .find_edge(a, b)
+                    .is_some_and(|edge| self.is_segment(edge))
+                {
+                    queue.segments.push_back((a, b));
+                }
+            }
+
+            queue.faces.push_back(self.faces[face].vertices);
+
+            let region = if orient2d(sides.a, sides.b, self.centroid(face)) > 0. {
+                sides.left
+            } else {
+                sides.right
+            };
+
+            if let Some(region) = region {
+                queue.face_regions.insert(face, region);
+            }
+        }
+    }
+}
+
+fn check_area_bound(max_area: f64) -> CadetResult<f64> {
+    if max_area > 0. {
+        Ok(max_area)
+    } else {
+        Err(CadetError::InvalidAreaBound(max_area))
+    }
+}
+
+/// Whether `p` lies strictly inside the circle with diameter `a`-`b`
+fn is_in_diametral_circle(a: DVec2, b: DVec2, p: DVec2) -> bool {
+    (a - p).dot(b - p) < 0.
+}
+
+/// Whether `p` lies strictly on the other side of the line through `a` and
+/// `b` than `inside`
+fn is_beyond(a: DVec2, b: DVec2, inside: DVec2, p: DVec2) -> bool {
+    orient2d(a, b, p) * orient2d(a, b, inside) < 0.
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use glam::DVec2;
+
+    use crate::{constraints::constraints::ConstraintSegment, error::CadetError};
+
+    use super::{AreaSeed, RefinementOptions, CDT};
+
+    fn square_domain() -> CDT {
+        CDT::from_points(&[
+            DVec2::new(-1., -1.),
+            DVec2::new(1., -1.),
+            DVec2::new(1., 1.),
+            DVec2::new(-1., 1.),
+        ])
+        .unwrap()
+    }
+
+    fn face_area(cdt: &CDT, vertices: [crate::vertex::VertexId; 3]) -> f64 {
+        let [a, b, c] = vertices.map(|v| cdt.vertices[v].position);
+        (b - a).perp_dot(c - a).abs() / 2.
+    }
+
+    fn assert_valid(cdt: &CDT) {
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+    }
+
+    #[test]
+    fn refined_faces_meet_the_angle_bound() {
+        let mut cdt = square_domain();
+        cdt.insert_constraint(&ConstraintSegment::generate_circle(DVec2::ZERO, 0.5, 30, 0))
+            .unwrap();
+        cdt.insert_constraint(&ConstraintSegment {
+            constraints: vec![DVec2::new(-0.9, -0.8), DVec2::new(0.9, -0.7)],
+            id: 1,
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Within the bound refinement is guaranteed to reach
+        let min_angle = 20.;
+        let report = cdt
+            .refine(&RefinementOptions {
+                min_angle,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(report.inserted > 0);
+        assert!(!report.truncated);
+
+        // No two segments meet at a small angle, so every angle is bounded
+        let smallest_angle = cdt
+            .faces
+            .values()
+            .flat_map(|face| {
+                let [a, b, c] = face.vertices.map(|v| cdt.vertices[v].position);
+                [(a, b, c), (b, c, a), (c, a, b)]
+                    .map(|(p, q, r)| (q - p).angle_to(r - p).abs().to_degrees())
+            })
+            .fold(f64::MAX, f64::min);
+        assert!(
+            smallest_angle >= min_angle - 1e-9,
+            "smallest angle of {} degrees",
+            smallest_angle
+        );
+
+        assert_valid(&cdt);
+    }
+
+    #[test]
+    fn refined_faces_meet_the_area_bound() {
+        let mut cdt = square_domain();
+        cdt.insert_constraint(&ConstraintSegment::generate_square(
+            DVec2::new(0.2, 0.1),
+            0.6,
+            0,
+        ))
+        .unwrap();
+
+        cdt.refine(&RefinementOptions {
+            min_angle: 0.,
+            max_area: Some(0.01),
+            ..Default::default()
+        })
+        .unwrap();
+
+        for face in cdt.faces.values() {
+            assert!(face_area(&cdt, face.vertices) <= 0.01);
+        }
+
+        assert_valid(&cdt);
+    }
+
+    #[test]
+    fn area_seeds_only_bound_their_region() {
+        let mut cdt = square_domain();
+        cdt.insert_constraint(&ConstraintSegment::generate_square(DVec2::ZERO, 1., 0))
+            .unwrap();
+
+        cdt.refine(&RefinementOptions {
+            area_seeds: vec![
+                AreaSeed {
+                    point: DVec2::ZERO,
+                    max_area: 0.005,
+                },
+                AreaSeed {
+                    point: DVec2::new(0.1, 0.1),
+                    max_area: 0.001,
+                },
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (inside, outside): (Vec<_>, Vec<_>) = cdt
+            .faces
+            .ids()
+            .partition(|&face| cdt.centroid(face).abs().max_element() < 0.5);
+
+        assert!(inside
+            .iter()
+            .all(|&face| face_area(&cdt, cdt.faces[face].vertices) <= 0.001));
+        assert!(outside
+            .iter()
+            .any(|&face| face_area(&cdt, cdt.faces[face].vertices) > 0.01));
+
+        assert_valid(&cdt);
+    }
+
+    #[test]
+    fn sizing_functions_bound_faces_at_their_centroid() {
+        let mut cdt = square_domain();
+        let sizing = |p: DVec2| 0.001 + 0.01 * (p.x + 1.);
+
+        cdt.refine(&RefinementOptions {
+            sizing: Some(Arc::new(sizing)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        for face in cdt.faces.ids() {
+            let max_area = sizing(cdt.centroid(face));
+            assert!(face_area(&cdt, cdt.faces[face].vertices) <= max_area);
+        }
+
+        // The faces grow from left to right
+        let left = cdt
+            .faces
+            .ids()
+            .filter(|&face| cdt.centroid(face).x < -0.5)
+            .count();
+        let right = cdt
+            .faces
+            .ids()
+            .filter(|&face| cdt.centroid(face).x > 0.5)
+            .count();
+        assert!(left > 2 * right);
+
+        assert_valid(&cdt);
+    }
+
+    #[test]
+    fn non_positive_area_bounds_are_rejected() {
+        let invalid = [
+            RefinementOptions {
+                max_area: Some(0.),
+                ..Default::default()
+            },
+            RefinementOptions {
+                area_seeds: vec![AreaSeed {
+                    point: DVec2::ZERO,
+                    max_area: -1.,
+                }],
+                ..Default::default()
+            },
+            RefinementOptions {
+                sizing: Some(Arc::new(|_| f64::NAN)),
+                ..Default::default()
+            },
+        ];
+
+        for options in invalid {
+            let mut cdt = square_domain();
+
+            assert!(matches!(
+                cdt.refine(&options),
+                Err(CadetError::InvalidAreaBound(_))
+            ));
+            assert_eq!(cdt.vertices.len(), 4);
+        }
+    }
+
+    #[test]
+    fn failed_refinements_leave_the_triangulation_unchanged() {
+        let mut cdt = square_domain();
+        cdt.insert_constraint(&ConstraintSegment::generate_circle(DVec2::ZERO, 0.5, 30, 0))
+            .unwrap();
+        let vertex_count = cdt.vertices.len();
+        let face_count = cdt.faces.len();
+
+        // The sizing function fails once some points are inserted
+        let calls = AtomicUsize::new(0);
+        let result = cdt.refine(&RefinementOptions {
+            sizing: Some(Arc::new(move |_| {
+                if calls.fetch_add(1, Ordering::Relaxed) < 100 {
+                    0.001
+                } else {
+                    -1.
+                }
+            })),
+            ..Default::default()
+        });
+
+        assert!(matches!(result, Err(CadetError::InvalidAreaBound(_))));
+        assert_eq!(cdt.vertices.len(), vertex_count);
+        assert_eq!(cdt.faces.len(), face_count);
+        assert_valid(&cdt);
+    }
+
+    #[test]
+    fn refinements_stopped_at_the_steiner_point_limit_are_reported() {
+        let options = RefinementOptions {
+            max_area: Some(0.001),
+            max_steiner_points: 10,
+            ..Default::default()
+        };
+
+        let mut cdt = square_domain();
+        let report = cdt.refine(&options).unwrap();
+        assert!(report.truncated);
+        assert!(report.inserted >= 10);
+        assert!(cdt
+            .faces
+            .ids()
+            .any(|face| face_area(&cdt, cdt.faces[face].vertices) > 0.001));
+
+        // The rest is inserted by refining again
+        let report = cdt
+            .refine(&RefinementOptions {
+                max_steiner_points: 100_000,
+                ..options
+            })
+            .unwrap();
+        assert!(!report.truncated);
+        assert!(cdt
+            .faces
+            .ids()
+            .all(|face| face_area(&cdt, cdt.faces[face].vertices) <= 0.001));
+
+        assert_valid(&cdt);
+    }
+}
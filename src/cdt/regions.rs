@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::DVec2;
+
+use crate::{
+    constraints::constraints::{Constraints, SegmentTag},
+    face::FaceId,
+    predicates::orient2d,
+    vertex::VertexId,
+};
+
+use super::cdt::CDT;
+
+/// How the winding number of a region decides whether it is inside a shape
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside if the constraint loops wind around the region an odd number
+    /// of times, so nested loops alternate between shapes and holes
+    #[default]
+    EvenOdd,
+    /// Inside if the constraint loops wind around the region at all
+    NonZero,
+}
+
+impl FillRule {
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/// Options for [`CDT::classify_regions`]
+#[derive(Clone, Debug, Default)]
+pub struct RegionOptions {
+    pub fill_rule: FillRule,
+    /// Points whose regions are holes, whatever their winding number
+    pub hole_seeds: Vec<DVec2>,
+    /// Removes the faces of holes and of the exterior once they are
+    /// classified, for example before exporting only the shapes
+    pub remove_holes: bool,
+}
+
+/// A set of faces connected without crossing a constrained edge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    /// Number of times the constraint loops wind counter-clockwise around
+    /// the region
+    pub winding: i32,
+    /// Whether the region is inside a shape by the fill rule or the segment
+    /// tags, and not a seeded hole
+    pub is_filled: bool,
+    /// Number of faces in the region
+    pub faces: usize,
+}
+
+impl CDT {
+    /// Splits the faces into regions separated by constrained edges, stores
+    /// the region index on every face and returns the regions.
+    ///
+    /// Whether a region is filled follows from the winding number of the
+    /// constraint loops around it. The loops are traced along the
+    /// constrained edges of the triangulation, so they include the Steiner
+    /// vertices and leave out removed constraints. Only constraints whose
+    /// edges close up count as loops, open ones separate regions without
+    /// winding around them. The winding number is taken at the centroid of
+    /// the largest face of the region, which keeps it away from rounded
+    /// constraint crossings.
+    ///
+    /// `constraints` only provides the segment tags, which take precedence
+    /// over the fill rule for closed constraints: regions inside a hole are
+    /// never filled, regions inside a region segment are always filled, and
+    /// if there are boundary segments, regions outside of all of them are
+    /// never filled.
+    pub fn classify_regions(
+        &mut self,
+        constraints: &Constraints,
+        options: &RegionOptions,
+    ) -> Vec<Region> {
+        let loops = self.constraint_loops();
+
+        let tagged = |tag| {
+            constraints
+                .constraint_segments
+                .iter()
+                .filter(move |segment| segment.tags.contains(&tag))
+                .filter_map(|segment| loops.get(&segment.id))
+        };
+        let has_boundary = tagged(SegmentTag::Boundary).next().is_some();
+
+        let mut regions = Vec::new();
+        let mut classified = HashSet::new();
+        let face_ids = self.faces.ids().collect::<Vec<_>>();
+
+        for face in face_ids {
+            if classified.contains(&face) {
+                continue;
+            }
+
+            let region_faces = self.flood_fill(face);
+
+            let representative = region_faces
+                .iter()
+                .map(|&face| (face, self.face_area(face)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(face, _)| self.centroid(face))
+                .unwrap();
+
+            let loop_winding = |polygons: &Vec<Vec<DVec2>>| {
+                polygons
+                    .iter()
+                    .map(|polygon| winding_number(polygon, representative))
+                    .sum::<i32>()
+            };
+
+            let winding = loops.values().map(loop_winding).sum();
+
+            let is_inside = |tag| tagged(tag).any(|polygons| loop_winding(polygons) != 0);
+
+            let is_filled = if is_inside(SegmentTag::Hole)
+                || (has_boundary && !is_inside(SegmentTag::Boundary))
+            {
+                false
+            } else {
+                is_inside(SegmentTag::Region) || options.fill_rule.is_inside(winding)
+            };
+
+            for &face in region_faces.iter() {
+                self.faces[face].region = Some(regions.len());
+                classified.insert(face);
+            }
+
+            regions.push(Region {
+                winding,
+                is_filled,
+                faces: region_faces.len(),
+            });
+        }
+
+        for seed in options.hole_seeds.iter() {
+            let region = self
+                .locate_face(seed)
+                .and_then(|face| self.faces[face].region);
+
+            if let Some(region) = region {
+                regions[region].is_filled = false;
+            }
+        }
+
+        if options.remove_holes {
+            self.remove_unfilled_faces(&regions);
+        }
+
+        regions
+    }
+
+    /// Polygons traced along the constrained edges of every closed
+    /// constraint, keyed by the constraint id.
+    ///
+    /// A constraint is closed if each of its vertices has an even number of
+    /// its edges. Where a constraint passes a vertex more than once, the
+    /// polygon goes on along the edge closest to straight ahead. The
+    /// vertices of a constraint are listed starting with its input points in
+    /// order, so every polygon is oriented to visit the three vertices
+    /// listed first in their order, like the constraint was inserted.
+    fn constraint_loops(&self) -> HashMap<usize, Vec<Vec<DVec2>>> {
+        let mut neighbors = HashMap::<usize, HashMap<VertexId, Vec<VertexId>>>::new();
+
+        for edge in self.edges.values() {
+            for &constraint in edge.crep.iter() {
+                let constraint_neighbors = neighbors.entry(constraint).or_default();
+                constraint_neighbors.entry(edge.a).or_default().push(edge.b);
+                constraint_neighbors.entry(edge.b).or_default().push(edge.a);
+            }
+        }
+
+        let position = |v: VertexId| self.vertices[v].position;
+        let mut loops = HashMap::new();
+
+        for (constraint, mut neighbors) in neighbors {
+            if neighbors.values().any(|vertices| vertices.len() % 2 != 0) {
+                continue;
+            }
+
+            let order = self
+                .constraints
+                .get(&constraint)
+                .map_or_else(HashMap::new, |vertices| {
+                    vertices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| (v, i))
+                        .collect::<HashMap<_, _>>()
+                });
+            let rank = |v: &VertexId| order.get(v).copied().unwrap_or(usize::MAX);
+
+            let mut starts = neighbors.keys().copied().collect::<Vec<_>>();
+            starts.sort_by_key(rank);
+
+            let mut polygons = Vec::new();
+
+            for start in starts {
+                while !neighbors[&start].is_empty() {
+                    let mut cycle = vec![start];
+                    let mut current = start;
+
+                    loop {
+                        let incoming = cycle
+                            .len()
+                            .checked_sub(2)
+                            .map(|i| (position(current) - position(cycle[i])).normalize());
+
+                        let candidates = &neighbors[&current];
+                        let i = match incoming {
+                            Some(incoming) => (0..candidates.len())
+                                .max_by(|&i, &j| {
+                                    let turn = |k: usize| {
+                                        (position(candidates[k]) - position(current))
+                                            .normalize()
+                                            .dot(incoming)
+                                    };
+                                    turn(i).total_cmp(&turn(j))
+                                })
+                                .unwrap(),
+                            None => 0,
+                        };
+
+                        let next = neighbors.get_mut(&current).unwrap().swap_remove(i);
+                        let back = neighbors.get_mut(&next).unwrap();
+                        back.swap_remove(back.iter().position(|&v| v == current).unwrap());
+
+                        // A loop through its start vertex goes on through it
+                        if next == start && neighbors[&start].is_empty() {
+                            break;
+                        }
+                        cycle.push(next);
+                        current = next;
+                    }
+
+                    // The three vertices listed first are visited in their
+                    // order, rotated to the first of them
+                    let mut listed = cycle.clone();
+                    listed.sort_by_key(rank);
+                    listed.dedup();
+                    let offset = |v: VertexId| {
+                        let i = cycle.iter().position(|&w| w == v).unwrap();
+                        let first = cycle.iter().position(|&w| w == listed[0]).unwrap();
+                        (i + cycle.len() - first) % cycle.len()
+                    };
+                    if listed.len() >= 3 && offset(listed[1]) > offset(listed[2]) {
+                        cycle.reverse();
+                    }
+
+                    polygons.push(cycle.into_iter().map(position).collect());
+                }
+            }
+
+            loops.insert(constraint, polygons);
+        }
+
+        loops
+    }
+
+    /// Removes the faces of regions that are not filled, and the vertices
+    /// that are left without faces
+    fn remove_unfilled_faces(&mut self, regions: &[Region]) {
+        let removed = self
+            .faces
+            .iter()
+            .filter(|(_, face)| face.region.is_some_and(|region| !regions[region].is_filled))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        let vertices = removed
+            .iter()
+            .flat_map(|&face| self.faces[face].vertices)
+            .collect::<HashSet<_>>();
+
+        for face in removed {
+            self.remove_face(face);
+        }
+
+        for vertex in vertices {
+            if self.sym_edges_by_vertices.contains_key(&vertex) {
+                continue;
+            }
+
+            self.vertices.remove(vertex);
+
+            for constraint_vertices in self.constraints.values_mut() {
+                constraint_vertices.retain(|&v| v != vertex);
+            }
+        }
+    }
+
+    fn face_area(&self, face: FaceId) -> f64 {
+        let [a, b, c] = self.faces[face].vertices.map(|v| self.vertices[v].position);
+
+        orient2d(a, b, c).abs() / 2.
+    }
+}
+
+/// Winding number of the polygon around `p`, counting counter-clockwise
+/// turns as positive. The polygon is closed from its last point back to its
+/// first.
+pub(crate) fn winding_number(polygon: &[DVec2], p: DVec2) -> i32 {
+    let mut winding = 0;
+
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+
+        if a.y <= p.y {
+            if b.y > p.y && orient2d(a, b, p) > 0. {
+                winding += 1;
+            }
+        } else if b.y <= p.y && orient2d(a, b, p) < 0. {
+            winding -= 1;
+        }
+    }
+
+    winding
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use crate::constraints::constraints::{ConstraintSegment, Constraints, SegmentTag};
+
+    use super::{winding_number, FillRule, RegionOptions, CDT};
+
+    fn grid(n: usize) -> Vec<DVec2> {
+        let step = 2. / (n - 1) as f64;
+
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| DVec2::new(i as f64, j as f64) * step - 1.))
+            .collect()
+    }
+
+    /// Whether the region of the face containing `point` is filled
+    fn is_filled_at(cdt: &CDT, regions: &[super::Region], point: DVec2) -> bool {
+        let face = cdt.locate_face(&point).unwrap();
+        regions[cdt.faces[face].region.unwrap()].is_filled
+    }
+
+    fn classify(segments: Vec<ConstraintSegment>) -> (CDT, Vec<super::Region>) {
+        let mut cdt = CDT::from_points(&grid(9)).unwrap();
+        for segment in segments.iter() {
+            cdt.insert_constraint(segment).unwrap();
+        }
+
+        let constraints = Constraints {
+            constraint_segments: segments,
+        };
+        let regions = cdt.classify_regions(&constraints, &RegionOptions::default());
+
+        (cdt, regions)
+    }
+
+    fn tagged(mut segment: ConstraintSegment, tag: SegmentTag) -> ConstraintSegment {
+        segment.tags.push(tag);
+        segment
+    }
+
+    #[test]
+    fn nested_loops_alternate_without_tags() {
+        let (cdt, regions) = classify(vec![
+            ConstraintSegment::generate_square(DVec2::ZERO, 1.5, 0),
+            ConstraintSegment::generate_square(DVec2::ZERO, 0.5, 1),
+        ]);
+
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.9, 0.9)));
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(0.6, 0.)));
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.1, 0.1)));
+    }
+
+    #[test]
+    fn open_polylines_do_not_wind_around_regions() {
+        let (cdt, regions) = classify(vec![
+            ConstraintSegment::generate_square(DVec2::new(-0.4, 0.), 0.8, 0),
+            // Encloses a region together with the side of the loop, which
+            // would be filled if the polyline was closed
+            ConstraintSegment {
+                constraints: vec![
+                    DVec2::new(0., -0.4),
+                    DVec2::new(0.6, -0.4),
+                    DVec2::new(0.6, 0.4),
+                    DVec2::new(0., 0.4),
+                ],
+                id: 1,
+                ..Default::default()
+            },
+            ConstraintSegment {
+                constraints: vec![DVec2::new(-0.9, -0.3), DVec2::new(-0.1, 0.5)],
+                id: 2,
+                ..Default::default()
+            },
+        ]);
+
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.3, 0.05)));
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.9, 0.9)));
+        // Both sides of the polyline cutting through the loop stay filled
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(-0.7, 0.3)));
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(-0.2, -0.2)));
+    }
+
+    #[test]
+    fn tagged_loops_override_the_fill_rule() {
+        let (cdt, regions) = classify(vec![
+            tagged(
+                ConstraintSegment::generate_square(DVec2::ZERO, 1.5, 0),
+                SegmentTag::Boundary,
+            ),
+            tagged(
+                ConstraintSegment::generate_square(DVec2::new(-0.4, 0.), 0.5, 1),
+                SegmentTag::Region,
+            ),
+            tagged(
+                ConstraintSegment::generate_square(DVec2::new(0.4, 0.), 0.5, 2),
+                SegmentTag::Hole,
+            ),
+        ]);
+
+        // Outside of the boundary
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.9, 0.9)));
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(0., 0.6)));
+        // Even winding, but tagged as a region of its own
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(-0.4, 0.05)));
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.4, 0.05)));
+    }
+
+    #[test]
+    fn tags_of_open_segments_are_ignored() {
+        let (cdt, regions) = classify(vec![
+            ConstraintSegment::generate_square(DVec2::ZERO, 1.5, 0),
+            tagged(
+                ConstraintSegment {
+                    constraints: vec![DVec2::new(-0.5, -0.5), DVec2::new(0.5, 0.5)],
+                    id: 1,
+                    ..Default::default()
+                },
+                SegmentTag::Boundary,
+            ),
+        ]);
+
+        assert!(is_filled_at(&cdt, &regions, DVec2::new(0.6, 0.)));
+        assert!(!is_filled_at(&cdt, &regions, DVec2::new(0.9, 0.9)));
+    }
+
+    /// Winding number of the region of the face containing `point`
+    fn winding_at(cdt: &CDT, regions: &[super::Region], point: DVec2) -> i32 {
+        let face = cdt.locate_face(&point).unwrap();
+        regions[cdt.faces[face].region.unwrap()].winding
+    }
+
+    #[test]
+    fn windings_follow_the_orientation_of_the_loops() {
+        let outer = ConstraintSegment::generate_square(DVec2::ZERO, 1.5, 0);
+        let mut inner = ConstraintSegment::generate_square(DVec2::ZERO, 0.5, 1);
+        inner.constraints.reverse();
+        // Crosses itself in the middle, its two halves wind opposite ways
+        let bowtie = ConstraintSegment {
+            constraints: vec![
+                DVec2::new(-0.65, -0.2),
+                DVec2::new(-0.35, 0.2),
+                DVec2::new(-0.35, -0.2),
+                DVec2::new(-0.65, 0.2),
+            ],
+            id: 2,
+            closed: true,
+            ..Default::default()
+        };
+
+        let mut cdt = CDT::from_points(&grid(9)).unwrap();
+        for segment in [&outer, &inner, &bowtie] {
+            cdt.insert_constraint(segment).unwrap();
+        }
+        let constraints = Constraints {
+            constraint_segments: vec![outer.clone(), inner, bowtie.clone()],
+        };
+        let options = RegionOptions {
+            fill_rule: FillRule::NonZero,
+            ..Default::default()
+        };
+        let regions = cdt.classify_regions(&constraints, &options);
+
+        let ring = DVec2::new(0.6, 0.);
+        assert_eq!(winding_at(&cdt, &regions, ring), 1);
+        assert_eq!(
+            winding_at(&cdt, &regions, ring),
+            winding_number(&outer.constraints, ring)
+        );
+        assert_eq!(winding_at(&cdt, &regions, DVec2::ZERO), 0);
+        assert!(!is_filled_at(&cdt, &regions, DVec2::ZERO));
+
+        let (left, right) = (DVec2::new(-0.6, 0.05), DVec2::new(-0.4, 0.05));
+        assert_eq!(winding_number(&bowtie.constraints, left), 1);
+        assert_eq!(winding_number(&bowtie.constraints, right), -1);
+        for point in [left, right] {
+            assert_eq!(
+                winding_at(&cdt, &regions, point),
+                1 + winding_number(&bowtie.constraints, point)
+            );
+        }
+    }
+
+    #[test]
+    fn removed_constraints_no_longer_wind_around_regions() {
+        let segments = vec![
+            ConstraintSegment::generate_square(DVec2::ZERO, 1.5, 0),
+            ConstraintSegment::generate_square(DVec2::ZERO, 0.5, 1),
+        ];
+        let (mut cdt, _) = classify(segments.clone());
+
+        cdt.remove_constraint(1).unwrap();
+
+        // The constraints passed in still contain the removed loop
+        let constraints = Constraints {
+            constraint_segments: segments,
+        };
+        let regions = cdt.classify_regions(&constraints, &RegionOptions::default());
+
+        assert_eq!(regions.iter().filter(|region| region.is_filled).count(), 1);
+        assert_eq!(winding_at(&cdt, &regions, DVec2::ZERO), 1);
+        assert!(is_filled_at(&cdt, &regions, DVec2::ZERO));
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashSet;
+
+use glam::DVec2;
+
+use crate::{
+    error::{CadetError, CadetResult},
+    locate_result::LocateResult,
+    vertex::VertexId,
+};
+
+use super::cdt::{ConstraintIntersection, CDT};
+
+impl CDT {
+    /// Moves a vertex to `position`, keeping its id.
+    ///
+    /// The vertex is taken out of the triangulation and connected again at
+    /// the new position. Its constrained edges follow it and are inserted
+    /// again, splitting any constraint they now cross; these crossings are
+    /// returned. The height is interpolated again at the new position.
+    /// Corners of the boundary cannot be moved.
+    pub fn move_vertex(
+        &mut self,
+        vertex: VertexId,
+        position: DVec2,
+    ) -> CadetResult<Vec<ConstraintIntersection>> {
+        if !self.vertices.contains(vertex) {
+            return Err(CadetError::UnknownVertex(vertex));
+        }
+
+        if self.vertices[vertex].position == position {
+            return Ok(Vec::new());
+        }
+
+        // Check the target before the triangulation is modified
+        match self.locate_point(&position) {
+            LocateResult::None => return Err(CadetError::PointOutsideDomain(position)),
+            LocateResult::Vertex(other) => {
+                return Err(CadetError::PositionOccupied {
+                    vertex: other,
+                    point: position,
+                })
+            }
+            _ => {}
+        }
+
+        let constrained_edges = self
+            .sym_edges_by_vertices
+            .get(&vertex)
+            .into_iter()
+            .flatten()
+            .map(|&sym_edge| self.sym_edges[sym_edge].edge)
+            .filter(|&edge| self.edges[edge].is_constrained())
+            .collect::<HashSet<_>>();
+
+        let carried = constrained_edges
+            .iter()
+            .map(|&edge| {
+                let (a, b) = self.edges[edge].edge_indices();
+                let neighbor = if a == vertex { b } else { a };
+
+                (edge, neighbor, std::mem::take(&mut self.edges[edge].crep))
+            })
+            .collect::<Vec<_>>();
+
+        let new_faces = match self.disconnect_vertex(vertex) {
+            Some(new_faces) => new_faces,
+            None => {
+                for (edge, _, crep) in carried {
+                    self.edges[edge].crep = crep;
+                }
+
+                return Err(CadetError::UnremovableVertex {
+                    vertex,
+                    reason: "it is a corner of the boundary".to_string(),
+                });
+            }
+        };
+
+        self.flip_face_edges(&new_faces);
+
+        self.vertices[vertex].position = position;
+
+        match self.locate_point(&position) {
+            LocateResult::Edge(edge) => {
+                self.vertices[vertex].height = self.edge_height(edge, position);
+                self.connect_vertex_on_edge(vertex, edge);
+            }
+            LocateResult::Face(face) => {
+                self.vertices[vertex].height = self.face_height(face, position);
+                self.connect_vertex_in_face(vertex, face);
+            }
+            _ => {
+                return Err(CadetError::TopologyCorrupt(format!(
+                    "Vertex {} could not be connected at its new position",
+                    vertex
+                )))
+            }
+        }
+
+        let mut intersections = Vec::new();
+
+        for (_, neighbor, crep) in carried {
+            let mut constraints = crep.into_iter().collect::<Vec<_>>();
+            constraints.sort_unstable();
+
+            for constraint in constraints {
+                intersections.extend(self.insert_segment(vertex, neighbor, constraint)?);
+            }
+        }
+
+        Ok(intersections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        constraints::constraints::ConstraintSegment, error::CadetError,
+        locate_result::LocateResult, vertex::VertexId,
+    };
+
+    use super::CDT;
+
+    fn grid(n: usize) -> Vec<DVec2> {
+        let step = 2. / (n - 1) as f64;
+
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| DVec2::new(i as f64, j as f64) * step - 1.))
+            .collect()
+    }
+
+    fn vertex_at(cdt: &CDT, point: DVec2) -> VertexId {
+        match cdt.locate_point(&point) {
+            LocateResult::Vertex(vertex) => vertex,
+            result => panic!("{:?} is not a vertex but {:?}", point, result),
+        }
+    }
+
+    fn polyline(points: Vec<DVec2>, id: usize) -> ConstraintSegment {
+        ConstraintSegment {
+            constraints: points,
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// Every constraint lists each of its vertices once, and every vertex
+    /// counts the constraints listing it
+    fn assert_constraint_lists(cdt: &CDT) {
+        for (&constraint, vertices) in cdt.constraints.iter() {
+            for (i, vertex) in vertices.iter().enumerate() {
+                assert!(
+                    !vertices[..i].contains(vertex),
+                    "constraint {} lists vertex {} twice",
+                    constraint,
+                    vertex
+                );
+            }
+        }
+
+        for (id, vertex) in cdt.vertices.iter() {
+            let listed = cdt
+                .constraints
+                .values()
+                .filter(|vertices| vertices.contains(&id))
+                .count();
+            assert_eq!(vertex.constraints, listed, "vertex {}", id);
+        }
+    }
+
+    #[test]
+    fn moved_vertices_take_the_height_at_their_new_position() {
+        let mut cdt = CDT::from_points(&grid(9)).unwrap();
+        let height = |p: DVec2| p.x + 2. * p.y;
+        for (_, vertex) in cdt.vertices.iter_mut() {
+            vertex.height = height(vertex.position);
+        }
+
+        let vertex = vertex_at(&cdt, DVec2::new(0.25, 0.5));
+
+        // Inside a face and on an edge of the grid
+        for position in [DVec2::new(-0.3, 0.1), DVec2::new(0.6, -0.25)] {
+            cdt.move_vertex(vertex, position).unwrap();
+
+            assert_eq!(cdt.vertices[vertex].position, position);
+            assert!((cdt.vertices[vertex].height - height(position)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn moving_a_vertex_across_a_constraint_splits_it() {
+        let mut cdt = CDT::from_points(&grid(9)).unwrap();
+
+        cdt.insert_constraint(&polyline(
+            vec![DVec2::new(-0.1, -0.9), DVec2::new(0.1, 0.9)],
+            0,
+        ))
+        .unwrap();
+        cdt.insert_constraint(&polyline(
+            vec![DVec2::new(-0.6, 0.1), DVec2::new(-0.3, -0.2)],
+            1,
+        ))
+        .unwrap();
+
+        let vertex = vertex_at(&cdt, DVec2::new(-0.3, -0.2));
+        let intersections = cdt.move_vertex(vertex, DVec2::new(0.5, -0.2)).unwrap();
+
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].constraints, vec![0, 1]);
+        assert!(cdt.constraints[&1].contains(&vertex));
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+        assert_constraint_lists(&cdt);
+    }
+
+    #[test]
+    fn crossings_moved_onto_their_constraint_are_listed_once() {
+        let mut cdt = CDT::from_points(&grid(9)).unwrap();
+
+        cdt.insert_constraint(&polyline(
+            vec![
+                DVec2::new(-0.9, -0.1),
+                DVec2::new(-0.3, -0.1),
+                DVec2::new(0.9, -0.1),
+            ],
+            0,
+        ))
+        .unwrap();
+        let intersections = cdt
+            .insert_constraint(&polyline(
+                vec![DVec2::new(0.1, -0.8), DVec2::new(0.1, 0.8)],
+                1,
+            ))
+            .unwrap();
+        let crossing = intersections[0].vertex;
+
+        // Off the crossing, onto another segment of the first constraint
+        cdt.move_vertex(crossing, DVec2::new(-0.6, -0.1)).unwrap();
+
+        assert_eq!(cdt.vertices[crossing].constraints, 2);
+        assert_constraint_lists(&cdt);
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+    }
+
+    #[test]
+    fn moves_onto_vertices_or_outside_the_domain_are_rejected() {
+        let mut cdt = CDT::from_points(&grid(5)).unwrap();
+        let vertex = vertex_at(&cdt, DVec2::ZERO);
+        let other = vertex_at(&cdt, DVec2::new(0.5, 0.5));
+
+        assert!(matches!(
+            cdt.move_vertex(vertex, DVec2::new(0.5, 0.5)),
+            Err(CadetError::PositionOccupied { vertex: occupant, .. }) if occupant == other
+        ));
+        assert!(matches!(
+            cdt.move_vertex(vertex, DVec2::new(2., 0.)),
+            Err(CadetError::PointOutsideDomain(_))
+        ));
+
+        // The triangulation is left as it was
+        assert_eq!(cdt.vertices[vertex].position, DVec2::ZERO);
+        assert_eq!(cdt.vertices.len(), 25);
+        assert!(cdt.validate().is_valid());
+    }
+
+    #[test]
+    fn random_moves_keep_the_triangulation_valid() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut random_point = |r: f64| DVec2::new(rng.gen_range(-r..r), rng.gen_range(-r..r));
+
+            let mut points = grid(2);
+            points.extend((0..100).map(|_| random_point(1.)));
+            let mut cdt = CDT::from_points(&points).unwrap();
+
+            for id in 0..5 {
+                let constraint = polyline((0..3).map(|_| random_point(0.9)).collect(), id);
+                cdt.insert_constraint(&constraint).unwrap();
+            }
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..20 {
+                let vertex = cdt.vertices.random_id(&mut rng).unwrap();
+                let position = DVec2::new(rng.gen_range(-0.9..0.9), rng.gen_range(-0.9..0.9));
+
+                match cdt.move_vertex(vertex, position) {
+                    Ok(_) => assert_eq!(cdt.vertices[vertex].position, position),
+                    // Corners of the boundary stay
+                    Err(CadetError::UnremovableVertex { .. }) => continue,
+                    Err(error) => panic!("seed {}: {}", seed, error),
+                }
+
+                let report = cdt.validate();
+                assert!(report.is_valid(), "seed {}: {}", seed, report);
+                assert!(report.non_delaunay_edges.is_empty(), "seed {}", seed);
+                assert_constraint_lists(&cdt);
+            }
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    edge::EdgeId,
+    error::{CadetError, CadetResult},
+    face::FaceId,
+    orientation::Orientation,
+    predicates::{in_circumcircle, in_triangle, orient2d, orientation},
+    vertex::VertexId,
+};
+
+use super::cdt::{ConstraintIntersection, CDT};
+
+impl CDT {
+    /// Removes an inserted constraint.
+    ///
+    /// The id is stripped from every edge representing it, and the Steiner
+    /// vertices splitting the constraint are removed once no other constraint
+    /// passes through them. Its input points stay in the triangulation. The freed edges are made Delaunay again.
+    pub fn remove_constraint(&mut self, id: usize) -> CadetResult<()> {
+        let vertices = self
+            .constraints
+            .remove(&id)
+            .ok_or(CadetError::UnknownConstraint(id))?;
+
+        let mut edge_stack = VecDeque::new();
+
+        for (edge_id, edge) in self.edges.iter_mut() {
+            if edge.crep.remove(&id) && !edge.is_constrained() {
+                edge_stack.push_back(edge_id);
+            }
+        }
+
+        for vertex in vertices {
+            let vertex_data = &mut self.vertices[vertex];
+            vertex_data.constraints = vertex_data.constraints.saturating_sub(1);
+
+            if vertex_data.constraints > 0 || !vertex_data.steiner {
+                continue;
+            }
+
+            if let Some(new_faces) = self.disconnect_vertex(vertex) {
+                self.vertices.remove(vertex);
+
+                for face in new_faces {
+                    for (a, b) in self.faces[face].edge_indices() {
+                        edge_stack.push_back(self.find_edge(a, b).unwrap());
+                    }
+                }
+            }
+        }
+
+        // Removed vertices take some of the freed edges with them
+        self.legalize_edges(edge_stack);
+
+        Ok(())
+    }
+
+    /// Removes a vertex and fills the star-shaped hole it leaves with
+    /// Delaunay ears.
+    ///
+    /// Constrained edges around the hole are kept. A vertex inside a single
+    /// constraint, with exactly two incident constrained edges, is removed by
+    /// reconnecting its two neighbors, which may cross other constraints;
+    /// these crossings are returned. Vertices where constraints end or meet,
+    /// and corners of the boundary, cannot be removed.
+    pub fn remove_vertex(&mut self, vertex: VertexId) -> CadetResult<Vec<ConstraintIntersection>> {
+        if !self.vertices.contains(vertex) {
+            return Err(CadetError::UnknownVertex(vertex));
+        }
+
+        let constrained_edges = self
+            .sym_edges_by_vertices
+            .get(&vertex)
+            .into_iter()
+            .flatten()
+            .map(|&sym_edge| self.sym_edges[sym_edge].edge)
+            .filter(|&edge| self.edges[edge].is_constrained())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let reconnect = match constrained_edges.as_slice() {
+            [] => None,
+            &[e1, e2] if self.edges[e1].crep == self.edges[e2].crep => {
+                let other_end = |edge: EdgeId| {
+                    let edge = &self.edges[edge];
+                    if edge.a == vertex {
+                        edge.b
+                    } else {
+                        edge.a
+                    }
+                };
+
+                Some((other_end(e1), other_end(e2), e1, e2))
+            }
+            _ => {
+                return Err(CadetError::UnremovableVertex {
+                    vertex,
+                    reason: "constraints end or meet in it".to_string(),
+                })
+            }
+        };
+
+        // The constraints are inserted again between the neighbors
+        let crep = match reconnect {
+            Some((_, _, e1, e2)) => {
+                self.edges[e1].crep.clear();
+                std::mem::take(&mut self.edges[e2].crep)
+            }
+            None => HashSet::new(),
+        };
+
+        let new_faces = match self.disconnect_vertex(vertex) {
+            Some(new_faces) => new_faces,
+            None => {
+                if let Some((_, _, e1, e2)) = reconnect {
+                    self.edges[e1].crep = crep.clone();
+                    self.edges[e2].crep = crep;
+                }
+
+                return Err(CadetError::UnremovableVertex {
+                    vertex,
+                    reason: "it is a corner of the boundary".to_string(),
+                });
+            }
+        };
+
+        self.vertices.remove(vertex);
+
+        for vertices in self.constraints.values_mut() {
+            vertices.retain(|&v| v != vertex);
+        }
+
+        self.flip_face_edges(&new_faces);
+
+        let mut intersections = Vec::new();
+
+        if let Some((u, w, _, _)) = reconnect {
+            let mut constraints = crep.into_iter().collect::<Vec<_>>();
+            constraints.sort_unstable();
+
+            for constraint in constraints {
+                intersections.extend(self.insert_segment(u, w, constraint)?);
+            }
+        }
+
+        Ok(intersections)
+    }
+
+    /// Removes the faces around a vertex without incident constrained edges,
+    /// and fills the star-shaped hole with Delaunay ears. The vertex itself
+    /// stays in the arena, unconnected.
+    ///
+    /// A vertex on the boundary is only disconnected when it lies on a
+    /// straight part of it, so the domain keeps its shape. Returns the faces
+    /// filling the hole, or `None` if the vertex cannot be disconnected, in
+    /// which case the triangulation is left untouched.
+    pub(crate) fn disconnect_vertex(&mut self, vertex: VertexId) -> Option<Vec<FaceId>> {
+        let sym_edges = self.sym_edges_by_vertices.get(&vertex)?.clone();
+
+        let is_constrained = sym_edges
+            .iter()
+            .any(|&sym_edge| self.edges[self.sym_edges[sym_edge].edge].is_constrained());
+
+        if is_constrained {
+            return None;
+        }
+
+        let faces = sym_edges
+            .iter()
+            .map(|&sym_edge| self.sym_edges[sym_edge].face)
+            .collect::<Vec<_>>();
+
+        let polygon = self.vertex_link(vertex, &faces)?;
+        let ears = self.triangulate_hole(&polygon)?;
+
+        let removed_constraints = self.face_edge_constraints(&faces);
+
+        for &face in faces.iter() {
+            self.remove_face(face);
+        }
+
+        let new_faces = ears
+            .into_iter()
+            .map(|ear| self.add_face(ear))
+            .collect::<Vec<_>>();
+
+        self.restore_edge_constraints(removed_constraints);
+
+        Some(new_faces)
+    }
+
+    /// The polygon around `vertex` formed by the edges of its faces opposite
+    /// to it, wound like the faces. Boundary vertices yield an open chain,
+    /// which is closed through the vertex only if it lies on a straight part
+    /// of the boundary.
+    fn vertex_link(&self, vertex: VertexId, faces: &[FaceId]) -> Option<Vec<VertexId>> {
+        let next = faces
+            .iter()
+            .map(|&face| {
+                let vertices = self.faces[face].vertices;
+                let i = vertices.iter().position(|&v| v == vertex).unwrap();
+
+                (vertices[(i + 1) % 3], vertices[(i + 2) % 3])
+            })
+            .collect::<HashMap<_, _>>();
+
+        if next.len() != faces.len() {
+            return None;
+        }
+
+        // A boundary vertex has a link vertex without a predecessor
+        let chain_start = next
+            .keys()
+            .find(|v| !next.values().any(|w| w == *v))
+            .copied();
+
+        let start = chain_start.unwrap_or(*next.keys().next()?);
+        let mut polygon = vec![start];
+
+        while let Some(&v) = next.get(polygon.last().unwrap()) {
+            if v == start {
+                break;
+            }
+            if polygon.len() > faces.len() {
+                return None;
+            }
+            polygon.push(v);
+        }
+
+        let is_closed = chain_start.is_none();
+        let expected_len = if is_closed {
+            faces.len()
+        } else {
+            faces.len() + 1
+        };
+
+        if polygon.len() != expected_len {
+            return None;
+        }
+
+        if !is_closed {
+            let first = self.vertices[polygon[0]].position;
+            let last = self.vertices[*polygon.last().unwrap()].position;
+            let p = self.vertices[vertex].position;
+
+            let is_straight = orient2d(last, p, first) == 0. && (first - p).dot(last - p) < 0.;
+
+            if !is_straight {
+                return None;
+            }
+        }
+
+        Some(polygon)
+    }
+
+    /// Ear clipping of the hole left by a removed vertex.
+    ///
+    /// Ears whose circumcircle is free of the other polygon vertices are
+    /// preferred, which yields the Delaunay triangulation of the hole.
+    pub(crate) fn triangulate_hole(&self, polygon: &[VertexId]) -> Option<Vec<[VertexId; 3]>> {
+        let mut polygon = polygon.to_vec();
+        let mut ears = Vec::new();
+
+        while polygon.len() > 3 {
+            let n = polygon.len();
+            let position = |v: VertexId| self.vertices[v].position;
+
+            let candidates = (0..n)
+                .map(|i| {
+                    (
+                        polygon[(i + n - 1) % n],
+                        polygon[i],
+                        polygon[(i + 1) % n],
+                        i,
+                    )
+                })
+                .filter(|&(a, b, c, _)| {
+                    // Convex corners of the clockwise polygon turn clockwise
+                    orientation(position(a), position(b), position(c)) == Orientation::Clockwise
+                        && polygon.iter().all(|&v| {
+                            v == a
+                                || v == b
+                                || v == c
+                                || !in_triangle(position(a), position(b), position(c), position(v))
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            let is_delaunay_ear = |&&(a, b, c, _): &&(VertexId, VertexId, VertexId, usize)| {
+                polygon
+                    .iter()
+                    .all(|&v| !in_circumcircle(position(a), position(b), position(c), position(v)))
+            };
+
+            let &(a, b, c, i) = candidates
+                .iter()
+                .find(is_delaunay_ear)
+                .or_else(|| candidates.first())?;
+
+            ears.push([a, b, c]);
+            polygon.remove(i);
+        }
+
+        let [a, b, c] = [polygon[0], polygon[1], polygon[2]];
+        let position = |v: VertexId| self.vertices[v].position;
+
+        if orientation(position(a), position(b), position(c)) != Orientation::Clockwise {
+            return None;
+        }
+
+        ears.push([a, b, c]);
+
+        Some(ears)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        constraints::constraints::ConstraintSegment, error::CadetError,
+        locate_result::LocateResult, vertex::VertexId,
+    };
+
+    use super::CDT;
+
+    fn grid(n: usize) -> Vec<DVec2> {
+        let step = 2. / (n - 1) as f64;
+
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| DVec2::new(i as f64, j as f64) * step - 1.))
+            .collect()
+    }
+
+    fn vertex_at(cdt: &CDT, point: DVec2) -> VertexId {
+        match cdt.locate_point(&point) {
+            LocateResult::Vertex(vertex) => vertex,
+            result => panic!("{:?} is not a vertex but {:?}", point, result),
+        }
+    }
+
+    fn polyline(points: Vec<DVec2>, id: usize) -> ConstraintSegment {
+        ConstraintSegment {
+            constraints: points,
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn removing_a_constraint_restores_the_delaunay_triangulation() {
+        let mut cdt = CDT::from_points(&grid(11)).unwrap();
+        let vertex_count = cdt.vertices.len();
+
+        let constraint = polyline(vec![DVec2::new(-0.9, -0.9), DVec2::new(0.9, 0.85)], 0);
+        cdt.insert_constraint(&constraint).unwrap();
+        cdt.remove_constraint(0).unwrap();
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+        // Only the end points of the constraint are left
+        assert_eq!(cdt.vertices.len(), vertex_count + 2);
+        assert!(cdt.vertices.values().all(|vertex| vertex.constraints == 0));
+        assert!(cdt.edges.values().all(|edge| !edge.is_constrained()));
+    }
+
+    #[test]
+    fn crossing_constraints_can_be_removed_in_any_order() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut random_point = |r: f64| DVec2::new(rng.gen_range(-r..r), rng.gen_range(-r..r));
+
+            let points = (0..200).map(|_| random_point(1.)).collect::<Vec<_>>();
+            let mut cdt = CDT::from_points(&points).unwrap();
+            let vertex_count = cdt.vertices.len();
+
+            for id in 0..10 {
+                let constraint = polyline((0..4).map(|_| random_point(0.5)).collect(), id);
+                cdt.insert_constraint(&constraint).unwrap();
+            }
+
+            // Every removal leaves the others in a Delaunay triangulation
+            for id in [3, 0, 9, 5, 1, 8, 2, 7, 4, 6] {
+                cdt.remove_constraint(id).unwrap();
+
+                let report = cdt.validate();
+                assert!(report.is_valid(), "seed {}: {}", seed, report);
+                assert!(report.non_delaunay_edges.is_empty(), "seed {}", seed);
+            }
+
+            assert!(cdt.constraints.is_empty());
+            // Only the input points of the constraints are left
+            assert_eq!(cdt.vertices.len(), vertex_count + 40);
+            assert!(cdt.vertices.values().all(|vertex| !vertex.steiner));
+        }
+    }
+
+    #[test]
+    fn removing_vertices_keeps_the_triangulation_delaunay() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = (0..300)
+            .map(|_| DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect::<Vec<_>>();
+        let mut cdt = CDT::from_points(&points).unwrap();
+
+        let vertices = cdt.vertices.ids().collect::<Vec<_>>();
+        let mut removed = 0;
+
+        for vertex in vertices.into_iter().step_by(3) {
+            // Corners of the convex hull stay
+            match cdt.remove_vertex(vertex) {
+                Ok(intersections) => {
+                    assert!(intersections.is_empty());
+                    removed += 1;
+                }
+                Err(CadetError::UnremovableVertex { .. }) => continue,
+                Err(error) => panic!("{}", error),
+            }
+
+            assert!(!cdt.vertices.contains(vertex));
+
+            let report = cdt.validate();
+            assert!(report.is_valid(), "{}", report);
+            assert!(report.non_delaunay_edges.is_empty());
+        }
+
+        assert!(removed > 50);
+    }
+
+    #[test]
+    fn removing_a_vertex_of_a_constraint_reconnects_its_neighbors() {
+        let mut cdt = CDT::from_points(&grid(5)).unwrap();
+
+        let left = DVec2::new(-0.5, 0.);
+        let right = DVec2::new(0.5, 0.);
+        cdt.insert_constraint(&polyline(vec![left, DVec2::ZERO, right], 0))
+            .unwrap();
+
+        let intersections = cdt.remove_vertex(vertex_at(&cdt, DVec2::ZERO)).unwrap();
+        assert!(intersections.is_empty());
+
+        let (left, right) = (vertex_at(&cdt, left), vertex_at(&cdt, right));
+        let edge = cdt.find_edge(left, right).unwrap();
+        assert!(cdt.edges[edge].crep.contains(&0));
+        assert_eq!(cdt.constraints[&0].len(), 2);
+
+        let report = cdt.validate();
+        assert!(report.is_valid(), "{}", report);
+        assert!(report.non_delaunay_edges.is_empty());
+    }
+
+    #[test]
+    fn vertices_where_constraints_meet_and_hull_corners_are_kept() {
+        let mut cdt = CDT::from_points(&grid(5)).unwrap();
+
+        cdt.insert_constraint(&polyline(vec![DVec2::new(-0.5, 0.), DVec2::ZERO], 0))
+            .unwrap();
+        cdt.insert_constraint(&polyline(vec![DVec2::ZERO, DVec2::new(0., 0.5)], 1))
+            .unwrap();
+
+        for point in [DVec2::ZERO, DVec2::new(-1., -1.)] {
+            let vertex = vertex_at(&cdt, point);
+
+            assert!(matches!(
+                cdt.remove_vertex(vertex),
+                Err(CadetError::UnremovableVertex { .. })
+            ));
+            assert!(cdt.vertices.contains(vertex));
+        }
+
+        assert!(cdt.validate().is_valid());
+    }
+}
@@ -1,244 +1,288 @@
-use std::{cell::RefCell, rc::Rc};
+use std::collections::HashSet;
 
 use glam::DVec2;
 
 use crate::{
-    edge::Edge,
-    face::Face,
-    sym_edge::SymEdge,
+    edge::{Edge, EdgeId},
+    error::{CadetError, CadetResult},
+    face::{Face, FaceId},
+    predicates::compare_angles,
+    sym_edge::{SymEdge, SymEdgeId},
     symmetric_compare::{Flipped, TupleOrdered},
-    vertex::Vertex,
+    vertex::{Vertex, VertexId},
 };
 
 use super::cdt::CDT;
 
 impl CDT {
-    pub fn add_face(&mut self, vertices: [Rc<RefCell<Vertex>>; 3]) -> Rc<RefCell<Face>> {
-        let edges = (0..vertices.len())
-            .map(|i| {
-                let a = vertices[i].borrow().index;
-                let b = vertices[(i + 1) % vertices.len()].clone().borrow().index;
-                (a, b)
-            })
-            .collect::<Vec<_>>();
-
-        let face = Rc::new(RefCell::new(Face {
-            id: self.face_id_counter,
+    pub fn add_face(&mut self, vertices: [VertexId; 3]) -> FaceId {
+        let face = Face {
             vertices,
-        }));
-
-        edges.iter().for_each(|edge| {
-            let index = self
-                .edges
-                .binary_search_by(|x| x.borrow().edge_indices().ordered().cmp(&edge.ordered()));
+            region: None,
+        };
 
-            if let Err(index) = index {
-                let edge = Rc::new(RefCell::new(Edge {
-                    a: self.vertices[edge.0].clone(),
-                    b: self.vertices[edge.1].clone(),
+        for (a, b) in face.edge_indices() {
+            if self.find_edge(a, b).is_none() {
+                let edge = self.edges.insert(Edge {
+                    a,
+                    b,
                     crep: Default::default(),
-                }));
+                });
 
-                self.edges.insert(index, edge.clone());
+                self.edges_by_vertices.insert((a, b).ordered(), edge);
             }
-        });
+        }
 
-        self.build_symedges_for_face(face.clone()).unwrap();
+        let face = self.faces.insert(face);
 
-        face.borrow()
-            .vertex_indices()
-            .iter()
-            .for_each(|&vertex_idx| {
-                self.build_rot_pointers_for_vertex_sym_edges(self.vertices[vertex_idx].clone());
-            });
+        self.build_symedges_for_face(face).unwrap();
+
+        for vertex in vertices {
+            self.build_rot_pointers_for_vertex_sym_edges(vertex);
+        }
 
-        self.faces.push(face.clone());
+        face
+    }
 
-        self.face_id_counter += 1;
+    pub fn remove_face(&mut self, face: FaceId) {
+        let face = self
+            .faces
+            .remove(face)
+            .expect("Face is not in the triangulation");
 
-        face.clone()
+        // Remove face from sym_edges_by_edges
+        for edge in face.edge_indices().iter() {
+            let to_remove = self.get_sym_edge_for_half_edge(edge).unwrap();
+
+            self.remove_sym_edge(to_remove);
+        }
     }
 
-    pub fn remove_face(&mut self, face: Rc<RefCell<Face>>) {
-        let face_borrowed = face.borrow();
+    /// Constraints of the edges of `faces`, keyed by their end points.
+    ///
+    /// Removing a face also drops its boundary edges, so operations that
+    /// rebuild faces take this snapshot first and pass it to
+    /// [`CDT::restore_edge_constraints`] once the new faces are in place.
+    pub(crate) fn face_edge_constraints(
+        &self,
+        faces: &[FaceId],
+    ) -> Vec<((VertexId, VertexId), HashSet<usize>)> {
+        faces
+            .iter()
+            .flat_map(|&face| self.faces[face].edge_indices())
+            .filter_map(|(a, b)| {
+                let edge = &self.edges[self.find_edge(a, b)?];
+                edge.is_constrained().then(|| ((a, b), edge.crep.clone()))
+            })
+            .collect()
+    }
 
-        let len_before = self.faces.len();
+    pub(crate) fn restore_edge_constraints(
+        &mut self,
+        constraints: Vec<((VertexId, VertexId), HashSet<usize>)>,
+    ) {
+        for ((a, b), crep) in constraints {
+            if let Some(edge) = self.find_edge(a, b) {
+                self.edges[edge].crep.extend(crep);
+            }
+        }
+    }
 
-        // Remove face from faces
-        self.faces.retain(|x| x.as_ptr() != face.as_ptr());
+    /// Faces reachable from `face` without crossing a constrained edge
+    pub(crate) fn flood_fill(&self, face: FaceId) -> Vec<FaceId> {
+        let mut region = vec![face];
+        let mut visited = HashSet::from([face]);
+        let mut i = 0;
 
-        assert_eq!(self.faces.len(), len_before - 1);
+        while let Some(&face) = region.get(i) {
+            i += 1;
 
-        // Remove face from sym_edges_by_edges
-        for edge in face_borrowed.edge_indices().iter() {
-            let to_remove = self.get_sym_edge_for_half_edge(edge).unwrap();
+            for (a, b) in self.faces[face].edge_indices() {
+                let is_constrained = self
+                    .find_edge(a, b)
+                    .is_some_and(|edge| self.edges[edge].is_constrained());
 
-            self.remove_sym_edge(to_remove);
+                if is_constrained {
+                    continue;
+                }
+
+                if let Some(neighbor) = self.get_sym_edge_for_half_edge(&(b, a)) {
+                    let neighbor = self.sym_edges[neighbor].face;
+                    if visited.insert(neighbor) {
+                        region.push(neighbor);
+                    }
+                }
+            }
         }
+
+        region
     }
 
-    pub fn add_vertex(&mut self, position: DVec2, constraints: usize) -> Rc<RefCell<Vertex>> {
-        let vertex = Vertex {
+    pub fn add_vertex(&mut self, position: DVec2, constraints: usize) -> VertexId {
+        self.vertices.insert(Vertex {
             position,
-            index: self.vertices.len(),
+            height: 0.,
             constraints,
-        };
-        let vertex = Rc::new(RefCell::new(vertex));
-        self.vertices.push(vertex.clone());
-        vertex
+            steiner: false,
+        })
+    }
+
+    pub fn find_edge(&self, a: VertexId, b: VertexId) -> Option<EdgeId> {
+        self.edges_by_vertices.get(&(a, b).ordered()).copied()
     }
 
-    pub fn build_symedges_for_face(&mut self, face: Rc<RefCell<Face>>) -> Result<(), String> {
+    pub fn build_symedges_for_face(&mut self, face: FaceId) -> CadetResult<()> {
         let mut face_symedges = Vec::new();
 
-        for (i, edge) in face.borrow().edge_indices().iter().enumerate() {
-            let vertex = face.borrow().vertices[i].clone();
-            let edge = self
-                .edges
-                .iter()
-                .find(|x| x.borrow().edge_indices().ordered() == edge.ordered())
-                .unwrap()
-                .clone();
-            let sym = Rc::new(RefCell::new(SymEdge {
-                vertex: vertex.clone(),
-                edge: edge.clone(),
-                face: face.clone(),
+        for (i, edge) in self.faces[face].edge_indices().iter().enumerate() {
+            let vertex = self.faces[face].vertices[i];
+            let edge_id = self.find_edge(edge.0, edge.1).ok_or_else(|| {
+                CadetError::TopologyCorrupt(format!("Edge {:?} of face is missing", edge))
+            })?;
+            let sym = self.sym_edges.insert(SymEdge {
+                vertex,
+                edge: edge_id,
+                face,
                 nxt: None,
                 rot: None,
-            }));
+            });
 
-            face_symedges.push(sym.clone());
+            face_symedges.push(sym);
 
             self.sym_edges_by_vertices
-                .entry(vertex.borrow().index)
+                .entry(vertex)
                 .or_default()
-                .push(sym.clone());
+                .push(sym);
 
-            self.sym_edges_by_half_edges
-                .insert(sym.borrow().edge_indices(), sym.clone());
+            self.sym_edges_by_half_edges.insert(*edge, sym);
         }
 
         for i in 0..3 {
-            let nxt = face_symedges[(i + 1) % 3].clone();
-            face_symedges[i].borrow_mut().nxt = Some(nxt);
+            let nxt = face_symedges[(i + 1) % 3];
+            self.sym_edges[face_symedges[i]].nxt = Some(nxt);
         }
 
         Ok(())
     }
 
-    pub fn get_sym_edge_for_half_edge(
-        &self,
-        edge: &(usize, usize),
-    ) -> Option<Rc<RefCell<SymEdge>>> {
-        self.sym_edges_by_half_edges.get(edge).cloned()
+    pub fn get_sym_edge_for_half_edge(&self, edge: &(VertexId, VertexId)) -> Option<SymEdgeId> {
+        self.sym_edges_by_half_edges.get(edge).copied()
     }
 
-    pub fn get_all_sym_edges_for_edge(&self, edge: Rc<RefCell<Edge>>) -> Vec<Rc<RefCell<SymEdge>>> {
+    pub fn get_all_sym_edges_for_edge(&self, edge: EdgeId) -> Vec<SymEdgeId> {
+        let edge_indices = self.edges[edge].edge_indices();
+
         [
-            self.get_sym_edge_for_half_edge(&edge.borrow().edge_indices()),
-            self.get_sym_edge_for_half_edge(&edge.borrow().edge_indices().flipped()),
+            self.get_sym_edge_for_half_edge(&edge_indices),
+            self.get_sym_edge_for_half_edge(&edge_indices.flipped()),
         ]
-        .iter()
-        .filter_map(|x| x.clone())
+        .into_iter()
+        .flatten()
         .collect()
     }
 
-    pub fn remove_sym_edge(&mut self, sym_edge: Rc<RefCell<SymEdge>>) {
-        let edge = sym_edge.borrow().edge.clone();
-        {
-            let sym_edge_borrowed = sym_edge.borrow();
-            let edge_indices = sym_edge_borrowed.edge_indices();
-            let vertex_index = sym_edge_borrowed.vertex.borrow().index;
+    /// Destination vertex of a SymEdge
+    pub fn sym_edge_b(&self, sym_edge: SymEdgeId) -> VertexId {
+        let sym_edge = &self.sym_edges[sym_edge];
+        let edge = &self.edges[sym_edge.edge];
+        if edge.a == sym_edge.vertex {
+            edge.b
+        } else {
+            edge.a
+        }
+    }
 
-            self.sym_edges_by_half_edges.remove(&edge_indices);
+    pub fn sym_edge_indices(&self, sym_edge: SymEdgeId) -> (VertexId, VertexId) {
+        (self.sym_edges[sym_edge].vertex, self.sym_edge_b(sym_edge))
+    }
 
-            let all_symedges_for_edge =
-                self.get_all_sym_edges_for_edge(sym_edge_borrowed.edge.clone());
+    /// The SymEdge of the same edge in the adjacent face
+    pub fn neighbor(&self, sym_edge: SymEdgeId) -> Option<SymEdgeId> {
+        let nxt = self.sym_edges[sym_edge].nxt?;
+        let neighbor = self.sym_edges[nxt].rot?;
 
-            if all_symedges_for_edge.is_empty() {
-                let len_before = self.edges.len();
-                self.edges
-                    .retain(|x| x.borrow().edge_indices().ordered() != edge_indices.ordered());
-                assert_eq!(self.edges.len(), len_before - 1);
-            }
+        let is_inverse = self.sym_edges[neighbor].edge == self.sym_edges[sym_edge].edge;
+        if is_inverse {
+            return Some(neighbor);
+        }
+        None
+    }
 
-            let vertex_entry = self.sym_edges_by_vertices.get_mut(&vertex_index).unwrap();
+    pub fn neighbor_face(&self, sym_edge: SymEdgeId) -> Option<FaceId> {
+        Some(self.sym_edges[self.neighbor(sym_edge)?].face)
+    }
 
-            let len_before = vertex_entry.len();
+    pub fn remove_sym_edge(&mut self, sym_edge: SymEdgeId) {
+        let edge_indices = self.sym_edge_indices(sym_edge);
+        let removed = self.sym_edges.remove(sym_edge).unwrap();
+        let vertex_index = removed.vertex;
 
-            vertex_entry.retain(|x| !Rc::ptr_eq(x, &sym_edge));
+        self.sym_edges_by_half_edges.remove(&edge_indices);
 
-            assert_eq!(vertex_entry.len(), len_before - 1);
+        let vertex_entry = self.sym_edges_by_vertices.get_mut(&vertex_index).unwrap();
 
-            if vertex_entry.is_empty() {
-                self.sym_edges_by_vertices.remove(&vertex_index);
-            }
-        }
+        let len_before = vertex_entry.len();
 
-        //Update rot pointers
-        self.build_rot_pointers_for_vertex_sym_edges(edge.borrow().a.clone());
-        self.build_rot_pointers_for_vertex_sym_edges(edge.borrow().b.clone());
+        vertex_entry.retain(|&x| x != sym_edge);
 
-        let which_is_rot_of_this = self
-            .sym_edges_by_half_edges
-            .iter()
-            .filter(|(_, sym)| {
-                let sym = sym.borrow();
-                let rot = sym.rot.clone();
-                match rot {
-                    Some(rot) => Rc::ptr_eq(&rot, &sym_edge),
-                    None => false,
-                }
-            })
-            .collect::<Vec<_>>();
+        assert_eq!(vertex_entry.len(), len_before - 1);
 
-        let is_any_rot_of_this = !which_is_rot_of_this.clone().is_empty();
+        if vertex_entry.is_empty() {
+            self.sym_edges_by_vertices.remove(&vertex_index);
+        }
 
-        assert!(!is_any_rot_of_this);
+        // The edge is only dropped once no SymEdge around either endpoint uses it
+        let is_edge_unused = [edge_indices.0, edge_indices.1]
+            .iter()
+            .filter_map(|vertex| self.sym_edges_by_vertices.get(vertex))
+            .flatten()
+            .all(|&other| self.sym_edges[other].edge != removed.edge);
+
+        if is_edge_unused {
+            self.edges.remove(removed.edge).unwrap();
+            if self.find_edge(edge_indices.0, edge_indices.1) == Some(removed.edge) {
+                self.edges_by_vertices.remove(&edge_indices.ordered());
+            }
+        }
+
+        //Update rot pointers
+        self.build_rot_pointers_for_vertex_sym_edges(edge_indices.0);
+        self.build_rot_pointers_for_vertex_sym_edges(edge_indices.1);
     }
 
-    pub fn build_rot_pointers_for_vertex_sym_edges(&mut self, vertex: Rc<RefCell<Vertex>>) {
-        let sym_edges = self.sym_edges_by_vertices.get(&vertex.borrow().index);
-        let sym_edges = match sym_edges {
+    pub fn build_rot_pointers_for_vertex_sym_edges(&mut self, vertex: VertexId) {
+        let sym_edges = match self.sym_edges_by_vertices.get(&vertex) {
             Some(sym_edges) => sym_edges,
             None => return,
         };
+        let position = self.vertices[vertex].position;
+
         let mut angle_to_sym_edges = std::vec::Vec::new();
         //Iterate over the symmetrical edges
-        for sym in sym_edges {
-            let sym_ref = sym.borrow();
-            let edge = sym_ref.edge.borrow();
-            let other_vertex = if edge.a.borrow().index == vertex.borrow().index {
-                edge.b.borrow()
-            } else {
-                edge.a.borrow()
-            };
-
-            let angle = (other_vertex.position - vertex.borrow().position).to_angle();
-
-            angle_to_sym_edges.push((angle, sym.clone()));
+        for &sym in sym_edges {
+            let other_vertex = self.sym_edge_b(sym);
+
+            angle_to_sym_edges.push((self.vertices[other_vertex].position, sym));
         }
 
-        //Sort by angle
-        angle_to_sym_edges.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        //Sort by descending angle
+        angle_to_sym_edges.sort_by(|a, b| compare_angles(position, b.0, a.0));
 
         if angle_to_sym_edges.len() < 2 {
-            angle_to_sym_edges[0].1.borrow_mut().rot = None;
+            self.sym_edges[angle_to_sym_edges[0].1].rot = None;
             return;
         }
 
         //Link the `rot` pointers to the first sym that is counter-clockwise
         for i in 0..angle_to_sym_edges.len() {
-            let current = angle_to_sym_edges[i].1.clone();
-            let next = angle_to_sym_edges[(i + 1) % angle_to_sym_edges.len()]
-                .1
-                .clone();
+            let current = angle_to_sym_edges[i].1;
+            let next = angle_to_sym_edges[(i + 1) % angle_to_sym_edges.len()].1;
 
-            current.borrow_mut().rot = Some(next.clone());
+            self.sym_edges[current].rot = Some(next);
         }
     }
 
-    pub fn build_sym_edges(&mut self) -> Result<(), String> {
+    pub fn build_sym_edges(&mut self) -> CadetResult<()> {
         self.validate_faces()?;
 
         // Step 2: Link `rot` pointers between symmetrical SymEdges
@@ -246,7 +290,7 @@ impl CDT {
         let vertices = self
             .sym_edges_by_vertices
             .keys()
-            .map(|index| self.vertices[*index].clone())
+            .copied()
             .collect::<Vec<_>>();
 
         for vertex in vertices {
@@ -256,12 +300,74 @@ impl CDT {
         Ok(())
     }
 
-    fn validate_faces(&mut self) -> Result<(), String> {
-        for (face_idx, face_data) in self.faces.iter().enumerate() {
-            if face_data.borrow().vertices.len() < 3 {
-                return Err(format!("Face {} has fewer than 3 vertices", face_idx));
+    fn validate_faces(&mut self) -> CadetResult<()> {
+        for (face_idx, face_data) in self.faces.iter() {
+            let missing_vertex = face_data
+                .vertices
+                .iter()
+                .find(|&&vertex| !self.vertices.contains(vertex));
+
+            if let Some(vertex) = missing_vertex {
+                return Err(CadetError::TopologyCorrupt(format!(
+                    "Face {} references the missing vertex {}",
+                    face_idx, vertex
+                )));
             }
         }
         Ok(())
     }
+
+    pub fn pretty_print_sym_edge(&self, sym_edge: SymEdgeId) {
+        let sym = &self.sym_edges[sym_edge];
+
+        // Create a table
+        let mut table = prettytable::Table::new();
+
+        // Set table titles
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("Field"),
+            prettytable::Cell::new("Value"),
+        ]));
+
+        // Add each field in the struct as a table row
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("vertex"),
+            prettytable::Cell::new(&format!("{:?}", sym.vertex)),
+        ]));
+
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("edge"),
+            prettytable::Cell::new(&format!("{:?}", self.edges[sym.edge].edge_indices())),
+        ]));
+
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("face"),
+            prettytable::Cell::new(&format!("{:?}", self.faces[sym.face].vertex_indices())),
+        ]));
+
+        let describe = |other: Option<SymEdgeId>| match other {
+            Some(other) => format!("{:?}", self.sym_edge_indices(other)),
+            None => "None".to_string(),
+        };
+
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("nxt"),
+            prettytable::Cell::new(&describe(sym.nxt)),
+        ]));
+
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new("rot"),
+            prettytable::Cell::new(&describe(sym.rot)),
+        ]));
+
+        if let Some(neighbor) = self.neighbor(sym_edge) {
+            table.add_row(prettytable::Row::new(vec![
+                prettytable::Cell::new("neighbor"),
+                prettytable::Cell::new(&describe(Some(neighbor))),
+            ]));
+        };
+
+        // Print the table
+        table.printstd();
+    }
 }
@@ -0,0 +1,348 @@
+use std::io::Write;
+
+use glam::DVec2;
+
+use crate::error::{CadetError, CadetResult};
+
+use super::{cdt::CDT, regions::Region};
+
+/// Options for [`CDT::export_to_svg`]
+#[derive(Clone, Debug)]
+pub struct SvgExportOptions {
+    /// Length of the longer side of the image in pixels, the other side
+    /// follows from the aspect ratio of the triangulation
+    pub size: f64,
+    /// Fills the faces with a color per region found by
+    /// [`CDT::classify_regions`]
+    pub fill_regions: bool,
+    /// The regions returned by [`CDT::classify_regions`]. When given, only
+    /// the filled regions are colored, so holes and the exterior stay black
+    pub regions: Vec<Region>,
+    /// Marks the vertices created while inserting constraints or refining
+    pub mark_steiner_vertices: bool,
+    pub vertex_labels: bool,
+    pub face_labels: bool,
+}
+
+impl Default for SvgExportOptions {
+    fn default() -> Self {
+        Self {
+            size: 1024.,
+            fill_regions: true,
+            regions: Vec::new(),
+            mark_steiner_vertices: true,
+            vertex_labels: false,
+            face_labels: false,
+        }
+    }
+}
+
+/// Fraction of the image left empty around the triangulation
+const MARGIN: f64 = 0.05;
+
+impl CDT {
+    /// Draws the triangulation as an SVG image for debugging, in the style of
+    /// the example images: white edges on a black background.
+    ///
+    /// Constrained edges are drawn thicker, with a color per constraint id;
+    /// an edge representing several constraints takes the color of the
+    /// smallest id. The y axis points up like in the model.
+    pub fn export_to_svg(&self, path: &str, options: &SvgExportOptions) -> CadetResult<()> {
+        if self.faces.is_empty() {
+            return Err(CadetError::EmptyModel);
+        }
+
+        let (min, max) = self
+            .vertices
+            .values()
+            .fold((DVec2::MAX, DVec2::MIN), |(min, max), vertex| {
+                (min.min(vertex.position), max.max(vertex.position))
+            });
+        let extent = (max - min).max(DVec2::splat(f64::EPSILON));
+
+        // Fitting the longer side keeps thin triangulations from stretching
+        // the image along the other one
+        let scale = options.size * (1. - 2. * MARGIN) / extent.max_element();
+        let offset = options.size * MARGIN;
+        let width = extent.x * scale + 2. * offset;
+        let height = extent.y * scale + 2. * offset;
+
+        // Image coordinates grow downwards
+        let to_image = |p: DVec2| {
+            DVec2::new(
+                offset + (p.x - min.x) * scale,
+                height - offset - (p.y - min.y) * scale,
+            )
+        };
+        let position = |v| to_image(self.vertices[v].position);
+
+        let stroke = options.size / 1000.;
+
+        let file = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = width,
+            h = height,
+        )?;
+        writeln!(writer, r#"<rect width="100%" height="100%" fill="black"/>"#)?;
+
+        if options.fill_regions {
+            writeln!(writer, r#"<g stroke="none" fill-opacity="0.35">"#)?;
+            for face in self.faces.values() {
+                let Some(region) = face.region else {
+                    continue;
+                };
+                if options.regions.get(region).is_some_and(|r| !r.is_filled) {
+                    continue;
+                }
+
+                let [a, b, c] = face.vertices.map(position);
+
+                writeln!(
+                    writer,
+                    r#"<polygon points="{},{} {},{} {},{}" fill="{}"/>"#,
+                    a.x,
+                    a.y,
+                    b.x,
+                    b.y,
+                    c.x,
+                    c.y,
+                    color(region),
+                )?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        writeln!(
+            writer,
+            r#"<g stroke="white" stroke-width="{}" stroke-linecap="round">"#,
+            stroke
+        )?;
+        for edge in self.edges.values().filter(|edge| !edge.is_constrained()) {
+            let (a, b) = (position(edge.a), position(edge.b));
+            writeln!(
+                writer,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                a.x, a.y, b.x, b.y
+            )?;
+        }
+        writeln!(writer, "</g>")?;
+
+        writeln!(
+            writer,
+            r#"<g stroke-width="{}" stroke-linecap="round">"#,
+            stroke * 3.
+        )?;
+        for edge in self.edges.values() {
+            let Some(&constraint) = edge.crep.iter().min() else {
+                continue;
+            };
+            let (a, b) = (position(edge.a), position(edge.b));
+
+            writeln!(
+                writer,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                color(constraint),
+            )?;
+        }
+        writeln!(writer, "</g>")?;
+
+        if options.mark_steiner_vertices {
+            writeln!(writer, r#"<g fill="red">"#)?;
+            for vertex in self.vertices.values().filter(|vertex| vertex.steiner) {
+                let p = to_image(vertex.position);
+                writeln!(
+                    writer,
+                    r#"<circle cx="{}" cy="{}" r="{}"/>"#,
+                    p.x,
+                    p.y,
+                    stroke * 3.
+                )?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        let font_size = stroke * 10.;
+
+        if options.face_labels {
+            writeln!(
+                writer,
+                r#"<g fill="yellow" font-size="{}" text-anchor="middle" dominant-baseline="middle">"#,
+                font_size
+            )?;
+            for (id, face) in self.faces.iter() {
+                let [a, b, c] = face.vertices.map(position);
+                let center = (a + b + c) / 3.;
+                writeln!(
+                    writer,
+                    r#"<text x="{}" y="{}">{}</text>"#,
+                    center.x, center.y, id
+                )?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        if options.vertex_labels {
+            writeln!(writer, r#"<g fill="cyan" font-size="{}">"#, font_size)?;
+            for (id, vertex) in self.vertices.iter() {
+                let p = to_image(vertex.position);
+                writeln!(
+                    writer,
+                    r#"<text x="{}" y="{}">{}</text>"#,
+                    p.x + stroke * 2.,
+                    p.y - stroke * 2.,
+                    id
+                )?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        writeln!(writer, "</svg>")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A distinct color for each index, with hues spread by the golden angle.
+/// The first hue is blue, away from the red Steiner vertex marks.
+fn color(index: usize) -> String {
+    let hue = (210. + index as f64 * 137.508) % 360.;
+    format!("hsl({:.1}, 90%, 60%)", hue)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use crate::{
+        constraints::constraints::{ConstraintSegment, Constraints},
+        error::CadetError,
+        RegionOptions,
+    };
+
+    use super::{SvgExportOptions, CDT};
+
+    fn export(cdt: &CDT, name: &str, options: &SvgExportOptions) -> String {
+        let path = std::env::temp_dir().join(format!("cadet-{}-{}.svg", name, std::process::id()));
+        cdt.export_to_svg(path.to_str().unwrap(), options).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn view_box(svg: &str) -> [f64; 4] {
+        let document = roxmltree::Document::parse(svg).unwrap();
+        let values = document
+            .root_element()
+            .attribute("viewBox")
+            .unwrap()
+            .split(' ')
+            .map(|value| value.parse().unwrap())
+            .collect::<Vec<f64>>();
+
+        values.try_into().unwrap()
+    }
+
+    fn rectangle(size: DVec2) -> CDT {
+        let corners = [DVec2::ZERO, DVec2::X, DVec2::ONE, DVec2::Y];
+        CDT::from_points(&corners.map(|corner| corner * size)).unwrap()
+    }
+
+    #[test]
+    fn the_longer_side_fills_the_image() {
+        let options = SvgExportOptions::default();
+
+        // 90% of the image holds the triangulation, 5% is left on each side
+        let wide = view_box(&export(&rectangle(DVec2::new(4., 1.)), "wide", &options));
+        assert_eq!(wide[..2], [0., 0.]);
+        assert!((wide[2] - 1024.).abs() < 1e-9);
+        assert!((wide[3] - (1024. * 0.9 / 4. + 1024. * 0.1)).abs() < 1e-9);
+
+        let tall = view_box(&export(&rectangle(DVec2::new(1., 4.)), "tall", &options));
+        assert!((tall[2] - wide[3]).abs() < 1e-9);
+        assert!((tall[3] - 1024.).abs() < 1e-9);
+
+        // A thin triangulation no longer stretches the image
+        let thin = view_box(&export(&rectangle(DVec2::new(1e-3, 1.)), "thin", &options));
+        assert!((thin[3] - 1024.).abs() < 1e-9);
+        assert!(thin[2] < 1024.);
+    }
+
+    #[test]
+    fn every_edge_face_and_steiner_vertex_is_drawn() {
+        let points = (0..5)
+            .flat_map(|i| (0..5).map(move |j| DVec2::new(i as f64, j as f64) * 0.5 - 1.))
+            .collect::<Vec<_>>();
+        let mut cdt = CDT::from_points(&points).unwrap();
+
+        let square = ConstraintSegment {
+            constraints: vec![
+                DVec2::new(-0.6, -0.6),
+                DVec2::new(0.6, -0.6),
+                DVec2::new(0.6, 0.6),
+                DVec2::new(-0.6, 0.6),
+            ],
+            id: 0,
+            closed: true,
+            ..Default::default()
+        };
+        let diagonal = ConstraintSegment {
+            constraints: vec![DVec2::new(-0.9, -0.8), DVec2::new(0.9, 0.8)],
+            id: 1,
+            ..Default::default()
+        };
+        let constraints = Constraints {
+            constraint_segments: vec![square, diagonal],
+        };
+
+        cdt.add_constraints(&constraints).unwrap();
+        let regions = cdt.classify_regions(&constraints, &RegionOptions::default());
+
+        let svg = export(
+            &cdt,
+            "elements",
+            &SvgExportOptions {
+                regions: regions.clone(),
+                ..Default::default()
+            },
+        );
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        let count = |tag: &str| {
+            document
+                .descendants()
+                .filter(|node| node.has_tag_name(tag))
+                .count()
+        };
+
+        let filled_faces = cdt
+            .faces
+            .values()
+            .filter(|face| face.region.is_some_and(|region| regions[region].is_filled))
+            .count();
+        let steiner_vertices = cdt.vertices.values().filter(|v| v.steiner).count();
+
+        assert!(filled_faces > 0);
+        assert_eq!(steiner_vertices, 2);
+        assert_eq!(count("polygon"), filled_faces);
+        assert_eq!(count("line"), cdt.edges.len());
+        assert_eq!(count("circle"), steiner_vertices);
+        assert_eq!(count("text"), 0);
+    }
+
+    #[test]
+    fn empty_triangulations_are_rejected() {
+        let path = std::env::temp_dir().join(format!("cadet-empty-{}.svg", std::process::id()));
+
+        assert!(matches!(
+            CDT::default().export_to_svg(path.to_str().unwrap(), &SvgExportOptions::default()),
+            Err(CadetError::EmptyModel)
+        ));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,450 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use crate::{
+    edge::EdgeId,
+    face::FaceId,
+    predicates::{compare_angles, orient2d},
+    sym_edge::SymEdgeId,
+    symmetric_compare::TupleOrdered,
+    vertex::VertexId,
+};
+
+use super::cdt::CDT;
+
+/// An inconsistency of the SymEdge structure found by [`CDT::validate`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The SymEdge is indexed but removed, or its face, edge or vertex is
+    /// not in the triangulation
+    DanglingSymEdge(SymEdgeId),
+    /// A vertex of the face is not in the triangulation
+    MissingFaceVertex { face: FaceId, vertex: VertexId },
+    /// Following `nxt` from the SymEdge does not return to it after three
+    /// steps within the same face
+    BrokenNxtCycle(SymEdgeId),
+    /// The `rot` pointers around the vertex do not visit all of its SymEdges
+    /// once, in angular order
+    BrokenRotFan(VertexId),
+    /// `neighbor` of the SymEdge is not the SymEdge of the opposite half-edge
+    BrokenNeighbor(SymEdgeId),
+    /// The face is degenerate or not wound clockwise
+    FaceNotClockwise(FaceId),
+    /// A half-edge of the face has no edge or SymEdge
+    MissingFaceEdge {
+        face: FaceId,
+        a: VertexId,
+        b: VertexId,
+    },
+    /// The edge is not indexed by its end points or not used by any face
+    DanglingEdge(EdgeId),
+    /// The edge represents a constraint that was never inserted
+    UnknownConstraint { edge: EdgeId, constraint: usize },
+    /// A constraint references a vertex that is not in the triangulation
+    MissingConstraintVertex { constraint: usize, vertex: VertexId },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::DanglingSymEdge(sym_edge) => {
+                write!(
+                    f,
+                    "SymEdge {} is removed or references a removed element",
+                    sym_edge
+                )
+            }
+            ValidationIssue::MissingFaceVertex { face, vertex } => {
+                write!(f, "Face {} references the missing vertex {}", face, vertex)
+            }
+            ValidationIssue::BrokenNxtCycle(sym_edge) => {
+                write!(f, "SymEdge {} is not in a nxt cycle of length 3", sym_edge)
+            }
+            ValidationIssue::BrokenRotFan(vertex) => {
+                write!(f, "The rot pointers around vertex {} are broken", vertex)
+            }
+            ValidationIssue::BrokenNeighbor(sym_edge) => {
+                write!(f, "The neighbor of SymEdge {} is not its inverse", sym_edge)
+            }
+            ValidationIssue::FaceNotClockwise(face) => {
+                write!(f, "Face {} is not wound clockwise", face)
+            }
+            ValidationIssue::MissingFaceEdge { face, a, b } => {
+                write!(f, "Half-edge ({}, {}) of face {} is missing", a, b, face)
+            }
+            ValidationIssue::DanglingEdge(edge) => {
+                write!(f, "Edge {} is not part of the triangulation", edge)
+            }
+            ValidationIssue::UnknownConstraint { edge, constraint } => write!(
+                f,
+                "Edge {} represents the unknown constraint {}",
+                edge, constraint
+            ),
+            ValidationIssue::MissingConstraintVertex { constraint, vertex } => write!(
+                f,
+                "Constraint {} references the missing vertex {}",
+                constraint, vertex
+            ),
+        }
+    }
+}
+
+/// Result of [`CDT::validate`]
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// Inconsistencies of the SymEdge structure
+    pub issues: Vec<ValidationIssue>,
+    /// Unconstrained edges whose opposite vertices lie in each other's
+    /// circumcircle
+    pub non_delaunay_edges: Vec<EdgeId>,
+}
+
+impl ValidationReport {
+    /// Whether the SymEdge structure is consistent
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Whether the structure is consistent and constrained Delaunay
+    pub fn is_delaunay(&self) -> bool {
+        self.is_valid() && self.non_delaunay_edges.is_empty()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} issues, {} non-Delaunay edges",
+            self.issues.len(),
+            self.non_delaunay_edges.len()
+        )?;
+
+        for issue in self.issues.iter() {
+            write!(f, "\n{}", issue)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CDT {
+    /// Checks the consistency of the SymEdge structure and whether the
+    /// triangulation is constrained Delaunay.
+    ///
+    /// Every check runs over the whole mesh, so this is meant for tests and
+    /// debugging rather than for use after every operation.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        // The other checks follow the references of the SymEdges
+        self.validate_sym_edge_references(&mut report);
+        if !report.is_valid() {
+            return report;
+        }
+
+        self.validate_nxt_cycles(&mut report);
+        self.validate_rot_fans(&mut report);
+        self.validate_neighbors(&mut report);
+        self.validate_face_edges(&mut report);
+        self.validate_edges(&mut report);
+        self.validate_constraints(&mut report);
+        self.find_non_delaunay_edges(&mut report);
+
+        report
+    }
+
+    fn validate_sym_edge_references(&self, report: &mut ValidationReport) {
+        for (id, sym_edge) in self.sym_edges.iter() {
+            let is_dangling = !self.faces.contains(sym_edge.face)
+                || !self.edges.contains(sym_edge.edge)
+                || !self.vertices.contains(sym_edge.vertex);
+
+            if is_dangling {
+                report.issues.push(ValidationIssue::DanglingSymEdge(id));
+            }
+        }
+
+        let indexed = self
+            .sym_edges_by_vertices
+            .values()
+            .flatten()
+            .chain(self.sym_edges_by_half_edges.values());
+
+        for &id in indexed {
+            if !self.sym_edges.contains(id) {
+                report.issues.push(ValidationIssue::DanglingSymEdge(id));
+            }
+        }
+
+        for (id, face) in self.faces.iter() {
+            if let Some(&vertex) = face.vertices.iter().find(|&&v| !self.vertices.contains(v)) {
+                report
+                    .issues
+                    .push(ValidationIssue::MissingFaceVertex { face: id, vertex });
+            }
+        }
+    }
+
+    fn validate_nxt_cycles(&self, report: &mut ValidationReport) {
+        for (id, sym_edge) in self.sym_edges.iter() {
+            let mut current = id;
+            let mut is_cycle = true;
+
+            for _ in 0..3 {
+                let next = self.sym_edges[current]
+                    .nxt
+                    .filter(|&next| self.sym_edges.contains(next))
+                    .filter(|&next| self.sym_edges[next].face == sym_edge.face)
+                    .filter(|&next| self.sym_edges[next].vertex == self.sym_edge_b(current));
+
+                match next {
+                    Some(next) => current = next,
+                    None => {
+                        is_cycle = false;
+                        break;
+                    }
+                }
+            }
+
+            if !is_cycle || current != id {
+                report.issues.push(ValidationIssue::BrokenNxtCycle(id));
+            }
+        }
+    }
+
+    fn validate_rot_fans(&self, report: &mut ValidationReport) {
+        for (&vertex, sym_edges) in self.sym_edges_by_vertices.iter() {
+            let position = self.vertices[vertex].position;
+            let end = |sym_edge: SymEdgeId| self.vertices[self.sym_edge_b(sym_edge)].position;
+
+            let is_fan = if sym_edges.len() < 2 {
+                sym_edges
+                    .iter()
+                    .all(|&sym_edge| self.sym_edges[sym_edge].rot.is_none())
+            } else {
+                // The fan turns by descending angle, wrapping around once
+                let mut visited = HashSet::new();
+                let mut wraps = 0;
+                let mut current = sym_edges[0];
+
+                for _ in 0..sym_edges.len() {
+                    let rot = match self.sym_edges[current].rot {
+                        Some(rot) if sym_edges.contains(&rot) => rot,
+                        _ => break,
+                    };
+
+                    if compare_angles(position, end(rot), end(current)) != Ordering::Less {
+                        wraps += 1;
+                    }
+
+                    visited.insert(rot);
+                    current = rot;
+                }
+
+                visited.len() == sym_edges.len() && current == sym_edges[0] && wraps == 1
+            };
+
+            if !is_fan {
+                report.issues.push(ValidationIssue::BrokenRotFan(vertex));
+            }
+        }
+    }
+
+    fn validate_neighbors(&self, report: &mut ValidationReport) {
+        for (id, _) in self.sym_edges.iter() {
+            let (a, b) = self.sym_edge_indices(id);
+            let inverse = self.get_sym_edge_for_half_edge(&(b, a));
+            let neighbor = self.neighbor(id);
+
+            let is_involutive = neighbor == inverse
+                && neighbor.is_none_or(|neighbor| self.neighbor(neighbor) == Some(id));
+
+            if !is_involutive {
+                report.issues.push(ValidationIssue::BrokenNeighbor(id));
+            }
+        }
+    }
+
+    fn validate_face_edges(&self, report: &mut ValidationReport) {
+        for (id, face) in self.faces.iter() {
+            let [a, b, c] = face.vertices.map(|v| self.vertices[v].position);
+
+            if orient2d(a, b, c) >= 0. {
+                report.issues.push(ValidationIssue::FaceNotClockwise(id));
+            }
+
+            for (a, b) in face.edge_indices() {
+                let has_sym_edge = self
+                    .get_sym_edge_for_half_edge(&(a, b))
+                    .is_some_and(|sym_edge| self.sym_edges[sym_edge].face == id);
+
+                if self.find_edge(a, b).is_none() || !has_sym_edge {
+                    report
+                        .issues
+                        .push(ValidationIssue::MissingFaceEdge { face: id, a, b });
+                }
+            }
+        }
+    }
+
+    fn validate_edges(&self, report: &mut ValidationReport) {
+        for (id, edge) in self.edges.iter() {
+            let (a, b) = edge.edge_indices();
+
+            let is_indexed = self.edges_by_vertices.get(&(a, b).ordered()) == Some(&id);
+            let is_used = !self.get_all_sym_edges_for_edge(id).is_empty();
+
+            if !is_indexed || !is_used {
+                report.issues.push(ValidationIssue::DanglingEdge(id));
+            }
+        }
+    }
+
+    fn validate_constraints(&self, report: &mut ValidationReport) {
+        for (id, edge) in self.edges.iter() {
+            for &constraint in edge.crep.iter() {
+                if !self.constraints.contains_key(&constraint) {
+                    report.issues.push(ValidationIssue::UnknownConstraint {
+                        edge: id,
+                        constraint,
+                    });
+                }
+            }
+        }
+
+        for (&constraint, vertices) in self.constraints.iter() {
+            for &vertex in vertices {
+                if !self.vertices.contains(vertex) {
+                    report
+                        .issues
+                        .push(ValidationIssue::MissingConstraintVertex { constraint, vertex });
+                }
+            }
+        }
+    }
+
+    fn find_non_delaunay_edges(&self, report: &mut ValidationReport) {
+        report.non_delaunay_edges = self
+            .edges
+            .ids()
+            .filter(|&edge| !self.is_edge_delaunay(edge))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use crate::{locate_result::LocateResult, symmetric_compare::TupleOrdered, vertex::VertexId};
+
+    use super::{ValidationIssue, CDT};
+
+    fn grid() -> CDT {
+        let points = (0..5)
+            .flat_map(|i| (0..5).map(move |j| DVec2::new(i as f64, j as f64 + 0.1 * i as f64)))
+            .collect::<Vec<_>>();
+
+        CDT::from_points(&points).unwrap()
+    }
+
+    fn vertex_at(cdt: &CDT, point: DVec2) -> VertexId {
+        match cdt.locate_point(&point) {
+            LocateResult::Vertex(vertex) => vertex,
+            result => panic!("{:?} is not a vertex but {:?}", point, result),
+        }
+    }
+
+    #[test]
+    fn delaunay_triangulation_is_valid() {
+        let report = grid().validate();
+
+        assert!(report.is_delaunay(), "{}", report);
+    }
+
+    #[test]
+    fn non_delaunay_edges_are_reported_apart_from_issues() {
+        let points = [
+            DVec2::new(0., 0.),
+            DVec2::new(4., 0.),
+            DVec2::new(2., 1.),
+            DVec2::new(2., -1.),
+        ];
+        let mut cdt = CDT::from_points(&points).unwrap();
+
+        // Moving the top vertex up keeps the faces clockwise, but the other
+        // diagonal becomes the Delaunay one
+        let top = vertex_at(&cdt, points[2]);
+        let bottom = vertex_at(&cdt, points[3]);
+        cdt.vertices[top].position = DVec2::new(2., 5.);
+
+        let report = cdt.validate();
+
+        assert!(report.is_valid(), "{}", report);
+        assert!(!report.is_delaunay());
+        assert_eq!(
+            report.non_delaunay_edges,
+            vec![cdt.find_edge(top, bottom).unwrap()]
+        );
+    }
+
+    #[test]
+    fn corrupted_structure_is_reported() {
+        let mut cdt = grid();
+
+        let edge = cdt.edges.ids().next().unwrap();
+        let (a, b) = cdt.edges[edge].edge_indices();
+        cdt.edges_by_vertices.remove(&(a, b).ordered());
+
+        let face = cdt.faces.ids().next().unwrap();
+        cdt.faces[face].vertices.swap(0, 1);
+
+        let issues = cdt.validate().issues;
+
+        assert!(issues.contains(&ValidationIssue::DanglingEdge(edge)));
+        assert!(issues.contains(&ValidationIssue::FaceNotClockwise(face)));
+        assert!(issues
+            .iter()
+            .any(|issue| matches!(issue, ValidationIssue::MissingFaceEdge { .. })));
+    }
+
+    #[test]
+    fn broken_rot_fan_is_reported() {
+        let mut cdt = grid();
+
+        let vertex = vertex_at(&cdt, DVec2::new(2., 2.2));
+        let sym_edge = cdt.sym_edges_by_vertices[&vertex][0];
+        cdt.sym_edges[sym_edge].rot = None;
+
+        let issues = cdt.validate().issues;
+
+        // Neighbors are found through the rot pointers as well
+        assert!(issues.contains(&ValidationIssue::BrokenRotFan(vertex)));
+    }
+
+    #[test]
+    fn unknown_constraints_and_missing_vertices_are_reported() {
+        let mut cdt = grid();
+
+        let edge = cdt.edges.ids().next().unwrap();
+        cdt.edges[edge].crep.insert(7);
+
+        // A handle to a removed vertex
+        let vertex = cdt.vertices.values().next().unwrap().clone();
+        let removed = cdt.vertices.insert(vertex);
+        cdt.vertices.remove(removed);
+        cdt.constraints.insert(3, vec![removed]);
+
+        let issues = cdt.validate().issues;
+
+        assert_eq!(issues.len(), 2);
+        assert!(issues.contains(&ValidationIssue::UnknownConstraint {
+            edge,
+            constraint: 7
+        }));
+        assert!(issues.contains(&ValidationIssue::MissingConstraintVertex {
+            constraint: 3,
+            vertex: removed
+        }));
+    }
+}
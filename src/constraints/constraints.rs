@@ -1,70 +1,389 @@
-use std::io::{BufRead, Write};
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+};
+
+use glam::{DVec2, DVec3};
 
-use fontdue::{
-    layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle},
-    Font,
+use crate::{
+    cdt::io::{missing_obj_vertex, read_obj},
+    error::{CadetError, CadetResult},
+    projection::Projection,
 };
-use glam::DVec2;
+
+/// Version of the constraint file format written by [`Constraints::export`]
+const FORMAT_VERSION: u32 = 2;
 
 pub struct Constraints {
     pub constraint_segments: Vec<ConstraintSegment>,
 }
 
+#[derive(Clone, Debug, Default)]
 pub struct ConstraintSegment {
     pub constraints: Vec<DVec2>,
     pub id: usize,
+    pub name: Option<String>,
+    /// Whether the last point connects back to the first. Loops do not repeat
+    /// their first point at the end
+    pub closed: bool,
+    pub tags: Vec<SegmentTag>,
+}
+
+/// Role of a constraint segment, as tagged in a constraint file. Tags only
+/// apply to closed segments, when [`CDT::classify_regions`] fills the regions
+/// inside them.
+///
+/// [`CDT::classify_regions`]: crate::CDT::classify_regions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SegmentTag {
+    /// The segment bounds a hole, which is never filled
+    Hole,
+    /// The segment bounds a region of its own, which is filled whatever the
+    /// fill rule
+    Region,
+    /// The segment bounds the domain, outside of which nothing is filled
+    Boundary,
+}
+
+impl SegmentTag {
+    fn parse(tag: &str) -> Option<Self> {
+        match tag {
+            "hole" => Some(SegmentTag::Hole),
+            "region" => Some(SegmentTag::Region),
+            "boundary" => Some(SegmentTag::Boundary),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SegmentTag::Hole => "hole",
+            SegmentTag::Region => "region",
+            SegmentTag::Boundary => "boundary",
+        }
+    }
 }
 
 impl Constraints {
-    pub fn load(model_path: &str) -> Self {
-        let file = std::fs::File::open(model_path).expect("Failed to open file");
+    /// Reads a constraint file.
+    ///
+    /// `#` starts a comment that runs to the end of the line. A file whose
+    /// first line is `version 2` lists its segments explicitly:
+    ///
+    /// ```text
+    /// version 2
+    ///
+    /// segment 3
+    /// name Outer wall
+    /// closed
+    /// tag boundary
+    /// 0 0
+    /// 1 0
+    /// 1 1
+    /// ```
+    ///
+    /// `segment` starts a segment, with an optional id that otherwise follows
+    /// the previous one, and the `name`, `closed` and `tag` lines after it
+    /// describe it. Tags are `hole`, `region` or `boundary`. Other files are
+    /// read in the legacy format, an `x y` point per line with blank lines
+    /// between segments, which are numbered in order.
+    pub fn load(model_path: &str) -> CadetResult<Self> {
+        let file = std::fs::File::open(model_path)?;
         let reader = std::io::BufReader::new(file);
-        let lines = reader.lines();
-        let mut constraint_lists = Vec::new();
-        let mut constraints = Vec::new();
-        let mut id = 0;
-
-        for line in lines {
-            let line = line.expect("Failed to read line");
-            let parts = line.split_whitespace().collect::<Vec<_>>();
-
-            if parts.is_empty() {
-                constraint_lists.push(ConstraintSegment { constraints, id });
-                constraints = Vec::new();
-                id += 1;
-                continue;
-            }
+        let mut lines = Vec::new();
 
-            let x = parts[0].parse::<f64>().expect("Failed to parse x");
-            let y = parts[1].parse::<f64>().expect("Failed to parse y");
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
 
-            constraints.push(DVec2 { x, y });
-        }
+            // Lines holding only a comment do not separate legacy segments
+            let content = match line.split_once('#') {
+                Some((content, _)) if content.trim().is_empty() => continue,
+                Some((content, _)) => content,
+                None => &line,
+            };
 
-        Constraints {
-            constraint_segments: constraint_lists,
+            lines.push((line_index + 1, content.trim().to_string()));
         }
+
+        let version = lines
+            .iter()
+            .find(|(_, content)| !content.is_empty())
+            .and_then(|(line, content)| {
+                let (keyword, version) = content.split_once(char::is_whitespace)?;
+                (keyword == "version").then(|| (*line, version.trim().to_string()))
+            });
+
+        let constraint_segments = match version {
+            Some((line, version)) if version == FORMAT_VERSION.to_string() => {
+                let start = lines.iter().position(|(l, _)| *l == line).unwrap() + 1;
+                parse_segments(&lines[start..])?
+            }
+            Some((line, version)) => {
+                return Err(CadetError::Parse {
+                    line,
+                    message: format!("Unsupported format version '{}'", version),
+                })
+            }
+            None => parse_legacy_segments(&lines)?,
+        };
+
+        Ok(Constraints {
+            constraint_segments,
+        })
     }
 
-    pub fn export(&self, model_path: &str) {
-        let file = std::fs::File::create(model_path).expect("Failed to create file");
+    /// Reads the line elements of an OBJ file as constraints, numbered in the
+    /// order of the file. The points are projected like the model the
+    /// constraints are inserted in.
+    pub fn from_obj(model_path: &str, projection: Projection) -> CadetResult<Self> {
+        projection.check()?;
+        let obj = read_obj(model_path)?;
+
+        let constraint_segments = obj
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(id, line)| {
+                let indices = match line {
+                    obj::raw::object::Line::P(indices) => indices.clone(),
+                    obj::raw::object::Line::PT(indices) => {
+                        indices.iter().map(|&(p, _)| p).collect()
+                    }
+                };
+
+                let constraints = indices
+                    .into_iter()
+                    .map(|i| {
+                        let &(x, y, z, _) = obj.positions.get(i).ok_or_else(missing_obj_vertex)?;
+                        Ok(projection
+                            .project(DVec3::new(x as f64, y as f64, z as f64))
+                            .0)
+                    })
+                    .collect::<CadetResult<_>>()?;
+
+                let mut segment = ConstraintSegment {
+                    constraints,
+                    id,
+                    ..Default::default()
+                };
+                segment.normalize();
+                Ok(segment)
+            })
+            .collect::<CadetResult<_>>()?;
+
+        Ok(Constraints {
+            constraint_segments,
+        })
+    }
+
+    /// Writes the constraints in the current version of the file format
+    pub fn export(&self, model_path: &str) -> CadetResult<()> {
+        let file = std::fs::File::create(model_path)?;
         let mut writer = std::io::BufWriter::new(file);
 
+        writeln!(writer, "version {}", FORMAT_VERSION)?;
+
         for segment in &self.constraint_segments {
+            writeln!(writer)?;
+            writeln!(writer, "segment {}", segment.id)?;
+
+            if let Some(name) = &segment.name {
+                writeln!(writer, "name {}", name)?;
+            }
+            if segment.closed {
+                writeln!(writer, "closed")?;
+            }
+            if !segment.tags.is_empty() {
+                let tags = segment
+                    .tags
+                    .iter()
+                    .map(|tag| tag.name())
+                    .collect::<Vec<_>>();
+                writeln!(writer, "tag {}", tags.join(" "))?;
+            }
+
             for constraint in &segment.constraints {
-                writeln!(writer, "{} {}", constraint.x, constraint.y)
-                    .expect("Failed to write to file");
+                writeln!(writer, "{} {}", constraint.x, constraint.y)?;
             }
-            writeln!(writer).expect("Failed to write to file");
         }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Reads the segments of a legacy file, numbered in order. Only the first two
+/// fields of a line are read.
+fn parse_legacy_segments(lines: &[(usize, String)]) -> CadetResult<Vec<ConstraintSegment>> {
+    let mut segments = Vec::new();
+    let mut constraints = Vec::new();
+
+    for (line, content) in lines {
+        let parts = content.split_whitespace().collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            if !constraints.is_empty() {
+                segments.push(ConstraintSegment {
+                    constraints: std::mem::take(&mut constraints),
+                    id: segments.len(),
+                    ..Default::default()
+                });
+            }
+            continue;
+        }
+
+        let x = parse_coordinate(*line, parts.first(), "x")?;
+        let y = parse_coordinate(*line, parts.get(1), "y")?;
+
+        constraints.push(DVec2 { x, y });
+    }
+
+    // The last segment does not need a blank line after it
+    if !constraints.is_empty() {
+        segments.push(ConstraintSegment {
+            constraints,
+            id: segments.len(),
+            ..Default::default()
+        });
     }
+
+    for segment in segments.iter_mut() {
+        segment.normalize();
+    }
+
+    Ok(segments)
+}
+
+/// Reads the segments of a file in the current format, following its
+/// version line
+fn parse_segments(lines: &[(usize, String)]) -> CadetResult<Vec<ConstraintSegment>> {
+    let mut segments: Vec<ConstraintSegment> = Vec::new();
+    let mut segment_lines = Vec::new();
+    let mut ids = HashSet::new();
+
+    for (line, content) in lines {
+        let line = *line;
+        let parse_error = |message: String| CadetError::Parse { line, message };
+
+        let (keyword, rest) = content
+            .split_once(char::is_whitespace)
+            .map_or((content.as_str(), ""), |(keyword, rest)| {
+                (keyword, rest.trim())
+            });
+
+        if keyword.is_empty() {
+            continue;
+        }
+
+        if keyword == "segment" {
+            let id = match rest {
+                "" => segments.last().map_or(0, |segment| segment.id + 1),
+                id => id
+                    .parse::<usize>()
+                    .map_err(|err| parse_error(format!("Invalid segment id '{}': {}", id, err)))?,
+            };
+
+            if !ids.insert(id) {
+                return Err(parse_error(format!("Duplicate segment id {}", id)));
+            }
+
+            segments.push(ConstraintSegment {
+                id,
+                ..Default::default()
+            });
+            segment_lines.push(line);
+            continue;
+        }
+
+        let segment = segments
+            .last_mut()
+            .ok_or_else(|| parse_error(format!("'{}' before the first segment", content)))?;
+
+        match keyword {
+            "name" if rest.is_empty() => return Err(parse_error("Missing name".to_string())),
+            "name" => segment.name = Some(rest.to_string()),
+            "closed" if rest.is_empty() => segment.closed = true,
+            "closed" => return Err(parse_error(format!("Unexpected '{}' after closed", rest))),
+            "tag" => {
+                for name in rest.split_whitespace() {
+                    let tag = SegmentTag::parse(name)
+                        .ok_or_else(|| parse_error(format!("Unknown tag '{}'", name)))?;
+
+                    if !segment.tags.contains(&tag) {
+                        segment.tags.push(tag);
+                    }
+                }
+            }
+            _ if keyword.parse::<f64>().is_ok() => {
+                let parts = content.split_whitespace().collect::<Vec<_>>();
+
+                if parts.len() > 2 {
+                    return Err(parse_error(format!(
+                        "Unexpected '{}' after the coordinates",
+                        parts[2..].join(" ")
+                    )));
+                }
+
+                let x = parse_coordinate(line, parts.first(), "x")?;
+                let y = parse_coordinate(line, parts.get(1), "y")?;
+
+                segment.constraints.push(DVec2 { x, y });
+            }
+            _ => return Err(parse_error(format!("Unknown keyword '{}'", keyword))),
+        }
+    }
+
+    for (segment, &line) in segments.iter_mut().zip(segment_lines.iter()) {
+        if segment.constraints.is_empty() {
+            return Err(CadetError::Parse {
+                line,
+                message: format!("Segment {} has no points", segment.id),
+            });
+        }
+
+        segment.normalize();
+    }
+
+    Ok(segments)
+}
+
+fn parse_coordinate(line: usize, part: Option<&&str>, name: &str) -> CadetResult<f64> {
+    let part = part.ok_or_else(|| CadetError::Parse {
+        line,
+        message: format!("Missing {} coordinate", name),
+    })?;
+
+    let value = part.parse::<f64>().map_err(|err| CadetError::Parse {
+        line,
+        message: format!("Invalid {} coordinate '{}': {}", name, part, err),
+    })?;
+
+    // Rust parses "nan" and "inf", which no triangulation can hold
+    if !value.is_finite() {
+        return Err(CadetError::Parse {
+            line,
+            message: format!("Non-finite {} coordinate '{}'", name, part),
+        });
+    }
+
+    Ok(value)
 }
 
 impl ConstraintSegment {
+    /// Drops a last point that repeats the first one and marks the segment
+    /// closed instead
+    pub fn normalize(&mut self) {
+        if self.constraints.len() > 2 && self.constraints.first() == self.constraints.last() {
+            self.constraints.pop();
+            self.closed = true;
+        }
+    }
+
     pub fn generate_circle(center: DVec2, r: f64, n: usize, id: usize) -> ConstraintSegment {
         let mut circle = Vec::new();
         let step = 2. * std::f64::consts::PI / n as f64;
-        for i in 0..n + 1 {
+        for i in 0..n {
             let x = center.x + r * f64::cos(i as f64 * step);
             let y = center.y + r * f64::sin(i as f64 * step);
             circle.push(DVec2 { x, y });
@@ -72,6 +391,8 @@ impl ConstraintSegment {
         ConstraintSegment {
             constraints: circle.to_vec(),
             id,
+            closed: true,
+            ..Default::default()
         }
     }
 
@@ -87,6 +408,7 @@ impl ConstraintSegment {
         ConstraintSegment {
             constraints: line.to_vec(),
             id,
+            ..Default::default()
         }
     }
 
@@ -113,6 +435,158 @@ impl ConstraintSegment {
         ConstraintSegment {
             constraints: square.to_vec(),
             id,
+            closed: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a file in the temporary directory
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("cadet_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn load_str(name: &str, contents: &str) -> CadetResult<Constraints> {
+        Constraints::load(&temp_file(&format!("{}.ct", name), contents))
+    }
+
+    fn points(segment: &ConstraintSegment) -> Vec<(f64, f64)> {
+        segment.constraints.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn from_obj_rejects_missing_vertices() {
+        let path = temp_file("missing_vertex.obj", "v 0 0 0\nv 1 0 0\nl 1 2 3\n");
+
+        let result = Constraints::from_obj(&path, Projection::default());
+
+        assert!(matches!(result, Err(CadetError::Obj(_))));
+    }
+
+    #[test]
+    fn legacy_files_are_read_with_comments_and_without_a_trailing_blank_line() {
+        let constraints = load_str(
+            "legacy",
+            "# Format: x y\n0 0\n1 0 5 # extra fields are ignored\n\n# next\n1 1\n2 2\n3 3\n1 1",
+        )
+        .unwrap();
+        let segments = &constraints.constraint_segments;
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].id, 0);
+        assert_eq!(points(&segments[0]), vec![(0., 0.), (1., 0.)]);
+        assert!(!segments[0].closed);
+
+        // A loop repeating its first point is closed instead
+        assert_eq!(segments[1].id, 1);
+        assert_eq!(points(&segments[1]), vec![(1., 1.), (2., 2.), (3., 3.)]);
+        assert!(segments[1].closed);
+    }
+
+    #[test]
+    fn legacy_files_round_trip_through_the_current_format() {
+        let legacy = load_str("legacy-round-trip", "0 0\n1 0\n\n2 2\n3 2\n3 3\n2 2\n").unwrap();
+
+        let path = temp_file("legacy_exported.ct", "");
+        legacy.export(&path).unwrap();
+        let exported = Constraints::load(&path).unwrap();
+
+        assert_eq!(exported.constraint_segments.len(), 2);
+        for (a, b) in legacy
+            .constraint_segments
+            .iter()
+            .zip(exported.constraint_segments.iter())
+        {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.closed, b.closed);
+            assert_eq!(points(a), points(b));
+        }
+    }
+
+    #[test]
+    fn segments_round_trip_with_their_metadata() {
+        let constraints = Constraints {
+            constraint_segments: vec![
+                ConstraintSegment {
+                    constraints: vec![DVec2::new(0., 0.), DVec2::new(4., 0.), DVec2::new(4., 4.)],
+                    id: 3,
+                    name: Some("Outer wall".to_string()),
+                    closed: true,
+                    tags: vec![SegmentTag::Boundary, SegmentTag::Region],
+                },
+                ConstraintSegment {
+                    constraints: vec![DVec2::new(0.125, -1.5), DVec2::new(1e-3, 2.)],
+                    id: 7,
+                    tags: vec![SegmentTag::Hole],
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let path = temp_file("round_trip.ct", "");
+        constraints.export(&path).unwrap();
+        let loaded = Constraints::load(&path).unwrap();
+
+        assert_eq!(loaded.constraint_segments.len(), 2);
+        for (a, b) in constraints
+            .constraint_segments
+            .iter()
+            .zip(loaded.constraint_segments.iter())
+        {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.closed, b.closed);
+            assert_eq!(a.tags, b.tags);
+            assert_eq!(points(a), points(b));
+        }
+    }
+
+    #[test]
+    fn segment_ids_follow_the_previous_one() {
+        let constraints =
+            load_str("ids", "version 2\nsegment 4\n0 0\n1 0\nsegment\n1 1\n2 1\n").unwrap();
+
+        let ids = constraints
+            .constraint_segments
+            .iter()
+            .map(|segment| segment.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![4, 5]);
+    }
+
+    #[test]
+    fn parse_errors_report_their_line() {
+        let cases = [
+            ("version 3\nsegment\n0 0\n", 1),
+            ("version 2\n0 0\n", 2),
+            ("version 2\n\nsegment\n0 0\n1 x\n", 5),
+            ("version 2\nsegment\n0\n", 3),
+            ("version 2\nsegment\n0 0 0\n", 3),
+            ("version 2\nsegment 1\n0 0\nsegment 1\n1 1\n", 4),
+            ("version 2\nsegment\ntag wall\n0 0\n", 3),
+            ("version 2\nsegment\nclosed twice\n0 0\n", 3),
+            ("version 2\nsegment\n0 0\nunknown\n", 4),
+            ("version 2\nsegment\n0 0\nsegment\nname Empty\n", 4),
+            ("version 2\nsegment\n0 0\n# comment\nnan 1\n", 5),
+            ("version 2\nsegment\n0 -inf\n", 3),
+            ("0 0\n1 0\n\ninf 1\n", 4),
+            ("0 0\n1 NaN\n", 2),
+        ];
+
+        for (i, (contents, expected_line)) in cases.into_iter().enumerate() {
+            match load_str(&format!("error-{}", i), contents) {
+                Err(CadetError::Parse { line, .. }) => {
+                    assert_eq!(line, expected_line, "{:?}", contents)
+                }
+                Err(error) => panic!("{:?} fails with {}", contents, error),
+                Ok(_) => panic!("{:?} is parsed", contents),
+            }
         }
     }
 }
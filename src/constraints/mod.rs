@@ -1 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod constraints;
+pub mod svg;
+pub mod text;
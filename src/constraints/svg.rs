@@ -0,0 +1,833 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use glam::{DAffine2, DMat2, DVec2};
+use roxmltree::{Document, Node, ParsingOptions};
+use svgtypes::{Length, LengthUnit, PathParser, PathSegment, PointsParser, Transform, ViewBox};
+
+use crate::{
+    error::{CadetError, CadetResult},
+    helper::flatten_bezier,
+};
+
+use super::constraints::{ConstraintSegment, Constraints};
+
+/// Upper bound on the number of segments a single arc is flattened into
+const MAX_ARC_SEGMENTS: f64 = 256.;
+
+/// Elements whose content is not drawn where it is defined
+const HIDDEN_CONTAINERS: [&str; 6] = ["defs", "clipPath", "mask", "symbol", "pattern", "marker"];
+
+/// CSS pixels per inch, which relates the absolute units to user units
+const PIXELS_PER_INCH: f64 = 96.;
+
+/// Font size assumed for lengths in `em` and `ex`, in CSS pixels
+const FONT_SIZE: f64 = 16.;
+
+/// Options for [`Constraints::from_svg_with_options`]
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    /// Largest distance between a curve and the segments replacing it, in
+    /// the user units of the `viewBox`
+    pub tolerance: f64,
+    /// Rectangle of the model, as its lower and upper corners, that the
+    /// `viewBox` is fitted into. The drawing keeps its aspect ratio and is
+    /// centred in the rectangle. Without it, the `viewBox` is fitted into
+    /// the `width` and `height` of the drawing in CSS pixels, with its top
+    /// left corner at the origin.
+    pub target: Option<(DVec2, DVec2)>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.1,
+            target: None,
+        }
+    }
+}
+
+impl Constraints {
+    /// Reads the outlines of an SVG file as constraints, with the default
+    /// [`SvgOptions`]
+    pub fn from_svg(path: &str) -> CadetResult<Self> {
+        Self::from_svg_with_options(path, &SvgOptions::default())
+    }
+
+    /// Reads the outlines of the `path`, `polygon`, `polyline`, `rect`,
+    /// `circle` and `ellipse` elements of an SVG file as constraints.
+    ///
+    /// Every subpath becomes a segment, numbered in the order of the file
+    /// and named after the `id` of its element. Subpaths ending with `Z` and
+    /// the closed shapes are closed segments. The transforms of the elements
+    /// and their groups are applied, and the y axis is flipped so the drawing
+    /// is upright in the model. Content of `defs` and other elements that are
+    /// only referenced elsewhere is skipped, as are `use` elements and
+    /// elements that are not displayed.
+    pub fn from_svg_with_options(path: &str, options: &SvgOptions) -> CadetResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let parsing_options = ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let document = Document::parse_with_options(&text, parsing_options).map_err(|err| {
+            CadetError::Parse {
+                line: err.pos().row as usize,
+                message: err.to_string(),
+            }
+        })?;
+
+        let view = view_transform(&document, options)?;
+        let mut constraint_segments = Vec::new();
+
+        for node in document.descendants().filter(|node| node.is_element()) {
+            let is_hidden = node.ancestors().any(|ancestor| {
+                HIDDEN_CONTAINERS.contains(&ancestor.tag_name().name())
+                    || is_not_displayed(ancestor)
+            });
+
+            if is_hidden {
+                continue;
+            }
+
+            let Some(path) = element_path(&document, node, view.size)? else {
+                continue;
+            };
+
+            let transform = view.transform * element_transform(&document, node)?;
+
+            // Curves are flattened before the transform, which stretches
+            // their deviation by at most its largest singular value
+            let stretch = largest_singular_value(transform.matrix2).max(f64::EPSILON);
+            let tolerance = options.tolerance * view.scale / stretch;
+
+            let mut builder = SubpathBuilder::default();
+            builder.follow(&path, tolerance);
+
+            for (points, closed) in builder.subpaths {
+                let mut segment = ConstraintSegment {
+                    constraints: points
+                        .into_iter()
+                        .map(|p| transform.transform_point2(p))
+                        .collect(),
+                    id: constraint_segments.len(),
+                    name: node.attribute("id").map(str::to_string),
+                    closed,
+                    ..Default::default()
+                };
+                segment.normalize();
+
+                constraint_segments.push(segment);
+            }
+        }
+
+        Ok(Constraints {
+            constraint_segments,
+        })
+    }
+}
+
+/// Mapping of the user units of the root element to the model
+struct View {
+    transform: DAffine2,
+    /// Number of model units per user unit
+    scale: f64,
+    /// Size of the `viewBox`, which percentages of lengths refer to
+    size: DVec2,
+}
+
+/// Fits the `viewBox` into the target rectangle, or into the size of the
+/// drawing if there is none
+fn view_transform(document: &Document, options: &SvgOptions) -> CadetResult<View> {
+    let flip = DAffine2::from_scale(DVec2::new(1., -1.));
+
+    let root = document.root_element();
+    let view_box = root
+        .attribute("viewBox")
+        .map(|view_box| {
+            ViewBox::from_str(view_box)
+                .map_err(|err| parse_error(document, root, format!("Invalid viewBox: {}", err)))
+        })
+        .transpose()?;
+
+    // A missing size is 100%, which is the size of the viewBox
+    let view_box_size = view_box.map_or(DVec2::ZERO, |view_box| DVec2::new(view_box.w, view_box.h));
+    let [width, height] = ["width", "height"].map(|name| {
+        root.attribute(name)
+            .map(|_| length(document, root, name, view_box_size))
+            .transpose()
+    });
+    let size = DVec2::new(
+        width?.unwrap_or(view_box_size.x),
+        height?.unwrap_or(view_box_size.y),
+    );
+
+    let view_box = view_box.unwrap_or(ViewBox::new(0., 0., size.x, size.y));
+
+    let (min, max) = match options.target {
+        Some(target) => target,
+        None => (DVec2::new(0., -size.y), DVec2::new(size.x, 0.)),
+    };
+
+    if view_box.w <= 0. || view_box.h <= 0. || min.x >= max.x || min.y >= max.y {
+        if options.target.is_none() {
+            return Ok(View {
+                transform: flip,
+                scale: 1.,
+                size: view_box_size,
+            });
+        }
+
+        return Err(parse_error(
+            document,
+            root,
+            "The drawing has no viewBox or size to map".to_string(),
+        ));
+    }
+
+    let scale = ((max.x - min.x) / view_box.w).min((max.y - min.y) / view_box.h);
+    let center = DVec2::new(view_box.x + view_box.w / 2., view_box.y + view_box.h / 2.);
+
+    let transform = DAffine2::from_translation((min + max) / 2.)
+        * DAffine2::from_scale(DVec2::splat(scale))
+        * flip
+        * DAffine2::from_translation(-center);
+
+    Ok(View {
+        transform,
+        scale,
+        size: DVec2::new(view_box.w, view_box.h),
+    })
+}
+
+/// Whether the element is hidden with `display: none`, as an attribute or
+/// in its style
+fn is_not_displayed(node: Node) -> bool {
+    let style_display = node.attribute("style").and_then(|style| {
+        // The last declaration wins
+        style
+            .rsplit(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .find(|(property, _)| property.trim() == "display")
+            .map(|(_, value)| value.trim())
+    });
+
+    style_display.or(node.attribute("display")) == Some("none")
+}
+
+/// Product of the transforms of the element and of its ancestors
+fn element_transform(document: &Document, node: Node) -> CadetResult<DAffine2> {
+    node.ancestors()
+        .filter(|ancestor| ancestor.is_element())
+        .try_fold(DAffine2::IDENTITY, |transform, ancestor| {
+            let Some(value) = ancestor.attribute("transform") else {
+                return Ok(transform);
+            };
+
+            let t = Transform::from_str(value).map_err(|err| {
+                parse_error(document, ancestor, format!("Invalid transform: {}", err))
+            })?;
+
+            Ok(DAffine2::from_cols_array(&[t.a, t.b, t.c, t.d, t.e, t.f]) * transform)
+        })
+}
+
+/// The path data describing the outline of a shape element, `None` for other
+/// elements
+fn element_path(
+    document: &Document,
+    node: Node,
+    view_box_size: DVec2,
+) -> CadetResult<Option<Vec<PathSegment>>> {
+    let data = match node.tag_name().name() {
+        "path" => node.attribute("d").unwrap_or_default().to_string(),
+        "polygon" | "polyline" => {
+            let points = PointsParser::from(node.attribute("points").unwrap_or_default())
+                .map(|(x, y)| format!("{} {}", x, y))
+                .collect::<Vec<_>>();
+
+            if points.is_empty() {
+                return Ok(None);
+            }
+
+            let close = if node.has_tag_name("polygon") {
+                " Z"
+            } else {
+                ""
+            };
+            format!("M {}{}", points.join(" L "), close)
+        }
+        "rect" => {
+            let [x, y, w, h] = ["x", "y", "width", "height"]
+                .map(|name| length(document, node, name, view_box_size));
+            let (x, y, w, h) = (x?, y?, w?, h?);
+
+            if w <= 0. || h <= 0. {
+                return Ok(None);
+            }
+
+            // A missing corner radius takes the value of the other one
+            let rx = node
+                .attribute("rx")
+                .map(|_| length(document, node, "rx", view_box_size));
+            let ry = node
+                .attribute("ry")
+                .map(|_| length(document, node, "ry", view_box_size));
+            let (rx, ry) = match (rx.transpose()?, ry.transpose()?) {
+                (Some(rx), Some(ry)) => (rx, ry),
+                (Some(r), None) | (None, Some(r)) => (r, r),
+                (None, None) => (0., 0.),
+            };
+            let (rx, ry) = (rx.clamp(0., w / 2.), ry.clamp(0., h / 2.));
+
+            if rx == 0. || ry == 0. {
+                format!("M {} {} H {} V {} H {} Z", x, y, x + w, y + h, x)
+            } else {
+                let arc = format!("A {} {} 0 0 1", rx, ry);
+                format!(
+                    "M {} {} H {} {arc} {} {} V {} {arc} {} {} H {} {arc} {} {} V {} {arc} {} {} Z",
+                    x + rx,
+                    y,
+                    x + w - rx,
+                    x + w,
+                    y + ry,
+                    y + h - ry,
+                    x + w - rx,
+                    y + h,
+                    x + rx,
+                    x,
+                    y + h - ry,
+                    y + ry,
+                    x + rx,
+                    y,
+                )
+            }
+        }
+        "circle" | "ellipse" => {
+            let cx = length(document, node, "cx", view_box_size)?;
+            let cy = length(document, node, "cy", view_box_size)?;
+            let (rx, ry) = if node.has_tag_name("circle") {
+                let r = length(document, node, "r", view_box_size)?;
+                (r, r)
+            } else {
+                (
+                    length(document, node, "rx", view_box_size)?,
+                    length(document, node, "ry", view_box_size)?,
+                )
+            };
+
+            if rx <= 0. || ry <= 0. {
+                return Ok(None);
+            }
+
+            let arc = format!("A {} {} 0 0 1", rx, ry);
+            format!(
+                "M {} {} {arc} {} {} {arc} {} {} {arc} {} {} {arc} {} {} Z",
+                cx + rx,
+                cy,
+                cx,
+                cy + ry,
+                cx - rx,
+                cy,
+                cx,
+                cy - ry,
+                cx + rx,
+                cy,
+            )
+        }
+        _ => return Ok(None),
+    };
+
+    let segments = PathParser::from(data.as_str())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| parse_error(document, node, format!("Invalid path data: {}", err)))?;
+
+    Ok(Some(segments))
+}
+
+/// A length attribute of the element in user units, 0 if it is missing.
+/// Percentages refer to the width or the height of the `viewBox` depending on
+/// the attribute, or to their normalized diagonal for radii of circles.
+fn length(document: &Document, node: Node, name: &str, view_box_size: DVec2) -> CadetResult<f64> {
+    let Some(value) = node.attribute(name) else {
+        return Ok(0.);
+    };
+
+    let length = Length::from_str(value).map_err(|err| {
+        parse_error(
+            document,
+            node,
+            format!("Invalid {} '{}': {}", name, value, err),
+        )
+    })?;
+
+    let factor = match length.unit {
+        LengthUnit::None | LengthUnit::Px => 1.,
+        LengthUnit::Em => FONT_SIZE,
+        LengthUnit::Ex => FONT_SIZE / 2.,
+        LengthUnit::In => PIXELS_PER_INCH,
+        LengthUnit::Cm => PIXELS_PER_INCH / 2.54,
+        LengthUnit::Mm => PIXELS_PER_INCH / 25.4,
+        LengthUnit::Pt => PIXELS_PER_INCH / 72.,
+        LengthUnit::Pc => PIXELS_PER_INCH / 6.,
+        LengthUnit::Percent => {
+            let reference = match name {
+                "x" | "cx" | "width" | "rx" => view_box_size.x,
+                "y" | "cy" | "height" | "ry" => view_box_size.y,
+                _ => (view_box_size.length_squared() / 2.).sqrt(),
+            };
+            reference / 100.
+        }
+    };
+
+    Ok(length.number * factor)
+}
+
+fn parse_error(document: &Document, node: Node, message: String) -> CadetError {
+    CadetError::Parse {
+        line: document.text_pos_at(node.range().start).row as usize,
+        message,
+    }
+}
+
+/// Largest factor by which the linear map stretches a vector
+fn largest_singular_value(matrix: DMat2) -> f64 {
+    let gram = matrix.transpose() * matrix;
+    let trace = gram.x_axis.x + gram.y_axis.y;
+    let determinant = gram.determinant();
+    let discriminant = (trace * trace / 4. - determinant).max(0.);
+
+    (trace / 2. + discriminant.sqrt()).sqrt()
+}
+
+/// Follows path data in absolute coordinates and collects the flattened
+/// subpaths together with whether they are closed
+#[derive(Default)]
+struct SubpathBuilder {
+    subpaths: Vec<(Vec<DVec2>, bool)>,
+    points: Vec<DVec2>,
+    current: DVec2,
+    start: DVec2,
+    /// Control point of the previous cubic or quadratic curve, reflected by
+    /// the smooth curves that follow it
+    last_cubic: Option<DVec2>,
+    last_quadratic: Option<DVec2>,
+}
+
+impl SubpathBuilder {
+    fn follow(&mut self, segments: &[PathSegment], tolerance: f64) {
+        for &segment in segments {
+            let mut last_cubic = None;
+            let mut last_quadratic = None;
+
+            match segment {
+                PathSegment::MoveTo { abs, x, y } => {
+                    self.finish(false);
+                    let p = self.absolute(abs, x, y);
+                    self.start = p;
+                    self.line_to(p);
+                }
+                PathSegment::LineTo { abs, x, y } => {
+                    let p = self.absolute(abs, x, y);
+                    self.line_to(p);
+                }
+                PathSegment::HorizontalLineTo { abs, x } => {
+                    let x = if abs { x } else { self.current.x + x };
+                    self.line_to(DVec2::new(x, self.current.y));
+                }
+                PathSegment::VerticalLineTo { abs, y } => {
+                    let y = if abs { y } else { self.current.y + y };
+                    self.line_to(DVec2::new(self.current.x, y));
+                }
+                PathSegment::CurveTo {
+                    abs,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let c1 = self.absolute(abs, x1, y1);
+                    let c2 = self.absolute(abs, x2, y2);
+                    let p = self.absolute(abs, x, y);
+                    self.curve_to(&[c1, c2, p], tolerance);
+                    last_cubic = Some(c2);
+                }
+                PathSegment::SmoothCurveTo { abs, x2, y2, x, y } => {
+                    let c1 = self.reflect(self.last_cubic);
+                    let c2 = self.absolute(abs, x2, y2);
+                    let p = self.absolute(abs, x, y);
+                    self.curve_to(&[c1, c2, p], tolerance);
+                    last_cubic = Some(c2);
+                }
+                PathSegment::Quadratic { abs, x1, y1, x, y } => {
+                    let c = self.absolute(abs, x1, y1);
+                    let p = self.absolute(abs, x, y);
+                    self.curve_to(&[c, p], tolerance);
+                    last_quadratic = Some(c);
+                }
+                PathSegment::SmoothQuadratic { abs, x, y } => {
+                    let c = self.reflect(self.last_quadratic);
+                    let p = self.absolute(abs, x, y);
+                    self.curve_to(&[c, p], tolerance);
+                    last_quadratic = Some(c);
+                }
+                PathSegment::EllipticalArc {
+                    abs,
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                } => {
+                    let p = self.absolute(abs, x, y);
+                    let radii = DVec2::new(rx.abs(), ry.abs());
+                    let points = arc_points(
+                        self.current,
+                        p,
+                        radii,
+                        x_axis_rotation.to_radians(),
+                        large_arc,
+                        sweep,
+                        tolerance,
+                    );
+
+                    for point in points {
+                        self.line_to(point);
+                    }
+                    self.current = p;
+                }
+                PathSegment::ClosePath { .. } => {
+                    self.finish(true);
+
+                    // A command other than a move continues from the start of
+                    // the closed subpath
+                    self.current = self.start;
+                    self.points.push(self.start);
+                }
+            }
+
+            self.last_cubic = last_cubic;
+            self.last_quadratic = last_quadratic;
+        }
+
+        self.finish(false);
+    }
+
+    fn absolute(&self, abs: bool, x: f64, y: f64) -> DVec2 {
+        if abs {
+            DVec2::new(x, y)
+        } else {
+            self.current + DVec2::new(x, y)
+        }
+    }
+
+    /// The first control point of a smooth curve, which mirrors the last
+    /// control point of the previous curve of the same kind
+    fn reflect(&self, control: Option<DVec2>) -> DVec2 {
+        control.map_or(self.current, |control| 2. * self.current - control)
+    }
+
+    fn line_to(&mut self, p: DVec2) {
+        if self.points.last() != Some(&p) {
+            self.points.push(p);
+        }
+        self.current = p;
+    }
+
+    fn curve_to(&mut self, controls: &[DVec2], tolerance: f64) {
+        let points = std::iter::once(self.current)
+            .chain(controls.iter().copied())
+            .collect::<Vec<_>>();
+
+        for point in flatten_bezier(&points, tolerance) {
+            self.line_to(point);
+        }
+    }
+
+    /// Ends the current subpath. A single point does not form a segment, and
+    /// two points cannot form a loop.
+    fn finish(&mut self, closed: bool) {
+        let mut points = std::mem::take(&mut self.points);
+
+        // The closing edge replaces a last line back to the start
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        if points.len() > 1 {
+            let closed = closed && points.len() > 2;
+            self.subpaths.push((points, closed));
+        }
+    }
+}
+
+/// Points along an elliptical arc of the path data, after its start, as
+/// described in the implementation notes of the SVG specification
+fn arc_points(
+    from: DVec2,
+    to: DVec2,
+    radii: DVec2,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    tolerance: f64,
+) -> Vec<DVec2> {
+    if from == to {
+        return Vec::new();
+    }
+    if radii.x == 0. || radii.y == 0. {
+        return vec![to];
+    }
+
+    let rotation = DMat2::from_angle(rotation);
+    let p = rotation.transpose() * ((from - to) / 2.);
+
+    // Radii too small to span the end points are scaled up
+    let lambda = (p / radii).length_squared();
+    let radii = if lambda > 1. {
+        radii * lambda.sqrt()
+    } else {
+        radii
+    };
+
+    let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+    let (px2, py2) = (p.x * p.x, p.y * p.y);
+    let factor = ((rx2 * ry2 - rx2 * py2 - ry2 * px2) / (rx2 * py2 + ry2 * px2))
+        .max(0.)
+        .sqrt();
+    let factor = if large_arc == sweep { -factor } else { factor };
+
+    let center_prime = factor * DVec2::new(radii.x * p.y / radii.y, -radii.y * p.x / radii.x);
+    let center = rotation * center_prime + (from + to) / 2.;
+
+    let angle = |u: DVec2, v: DVec2| u.perp_dot(v).atan2(u.dot(v));
+    let start = (p - center_prime) / radii;
+    let end = (-p - center_prime) / radii;
+
+    let start_angle = angle(DVec2::X, start);
+    let mut sweep_angle = angle(start, end);
+
+    if !sweep && sweep_angle > 0. {
+        sweep_angle -= 2. * PI;
+    } else if sweep && sweep_angle < 0. {
+        sweep_angle += 2. * PI;
+    }
+
+    // Chords spanning `step` deviate by at most the tolerance from a circle
+    // of the largest radius
+    let radius = radii.max_element();
+    let step = 2. * (1. - (tolerance / radius).min(1.)).acos();
+    let segments = (sweep_angle.abs() / step).ceil();
+    let segments = if segments.is_finite() {
+        segments.clamp(1., MAX_ARC_SEGMENTS) as usize
+    } else {
+        MAX_ARC_SEGMENTS as usize
+    };
+
+    (1..=segments)
+        .map(|i| {
+            if i == segments {
+                return to;
+            }
+
+            let theta = start_angle + sweep_angle * i as f64 / segments as f64;
+            center + rotation * (radii * DVec2::new(theta.cos(), theta.sin()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes an SVG file with the given root attributes and content
+    fn temp_svg(name: &str, attributes: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("cadet_{}_{}.svg", std::process::id(), name));
+        let text = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" {}>\n{}\n</svg>\n",
+            attributes, content
+        );
+        std::fs::write(&path, text).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn path_segments(name: &str, d: &str) -> Vec<ConstraintSegment> {
+        let path = temp_svg(name, "", &format!("<path d=\"{}\"/>", d));
+        Constraints::from_svg(&path).unwrap().constraint_segments
+    }
+
+    fn points(segment: &ConstraintSegment) -> Vec<(f64, f64)> {
+        segment.constraints.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    fn assert_close(a: DVec2, b: DVec2) {
+        assert!(a.distance(b) < 1e-9, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn commands_after_a_close_start_from_the_closed_subpath() {
+        let segments = path_segments("close_then_line", "M0 0 L1 0 L1 1 Z L2 2");
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(points(&segments[0]), vec![(0., 0.), (1., 0.), (1., -1.)]);
+        assert!(segments[0].closed);
+        assert_eq!(points(&segments[1]), vec![(0., 0.), (2., -2.)]);
+        assert!(!segments[1].closed);
+
+        let segments = path_segments("close_then_relative", "M1 1 h1 v1 z l1 0 m0 1 l1 0");
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(points(&segments[1]), vec![(1., -1.), (2., -1.)]);
+        assert_eq!(points(&segments[2]), vec![(2., -2.), (3., -2.)]);
+    }
+
+    #[test]
+    fn degenerate_subpaths_are_dropped_or_left_open() {
+        let segments = path_segments(
+            "degenerate",
+            "M0 0 M1 1 L2 1 Z M3 3 Z M4 4 L4 4 L5 5 L4 4 Z",
+        );
+
+        assert_eq!(segments.len(), 2);
+        // Two points cannot form a loop
+        assert_eq!(points(&segments[0]), vec![(1., -1.), (2., -1.)]);
+        assert!(!segments[0].closed);
+        // Neither does a line there and back
+        assert_eq!(points(&segments[1]), vec![(4., -4.), (5., -5.)]);
+        assert!(!segments[1].closed);
+    }
+
+    #[test]
+    fn curves_and_arcs_end_on_their_end_points() {
+        let segments = path_segments(
+            "curves",
+            "M0 0 Q1 1 2 0 T4 0 C5 1 6 1 7 0 S9 -1 10 0 A2 1 30 1 0 14 0 a0 1 0 0 1 1 0",
+        );
+
+        assert_eq!(segments.len(), 1);
+        let constraints = &segments[0].constraints;
+        for end in [2., 4., 7., 10., 14., 15.] {
+            assert!(constraints
+                .iter()
+                .any(|p| p.distance(DVec2::new(end, 0.)) < 1e-9));
+        }
+        assert_close(*constraints.last().unwrap(), DVec2::new(15., 0.));
+    }
+
+    #[test]
+    fn circles_stay_within_the_tolerance() {
+        let path = temp_svg("circle", "", "<circle cx=\"5\" cy=\"5\" r=\"4\"/>");
+        let options = SvgOptions {
+            tolerance: 0.01,
+            ..Default::default()
+        };
+        let segments = Constraints::from_svg_with_options(&path, &options)
+            .unwrap()
+            .constraint_segments;
+
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].closed);
+
+        let center = DVec2::new(5., -5.);
+        let constraints = &segments[0].constraints;
+        for (i, &p) in constraints.iter().enumerate() {
+            let q = constraints[(i + 1) % constraints.len()];
+            assert!((p.distance(center) - 4.).abs() < 1e-9);
+            assert!(4. - ((p + q) / 2.).distance(center) <= 0.01);
+        }
+    }
+
+    #[test]
+    fn view_box_is_fitted_into_the_size_of_the_drawing() {
+        let path = temp_svg(
+            "units",
+            "width=\"20mm\" height=\"1in\" viewBox=\"10 0 2 1\"",
+            "<path d=\"M10 0 L12 1\"/>",
+        );
+        let segments = Constraints::from_svg(&path).unwrap().constraint_segments;
+
+        // The viewBox is scaled to fit the width and centred vertically
+        let width = 20. * PIXELS_PER_INCH / 25.4;
+        let height = PIXELS_PER_INCH;
+        let offset = (height - width / 2.) / 2.;
+        assert_close(segments[0].constraints[0], DVec2::new(0., -offset));
+        assert_close(
+            segments[0].constraints[1],
+            DVec2::new(width, -offset - width / 2.),
+        );
+    }
+
+    #[test]
+    fn view_box_is_fitted_into_the_target() {
+        let path = temp_svg(
+            "target",
+            "width=\"50%\" viewBox=\"0 0 10 20\"",
+            "<path d=\"M0 0 L10 20\"/>",
+        );
+        let options = SvgOptions {
+            target: Some((DVec2::new(-1., -1.), DVec2::new(1., 1.))),
+            ..Default::default()
+        };
+        let segments = Constraints::from_svg_with_options(&path, &options)
+            .unwrap()
+            .constraint_segments;
+
+        assert_close(segments[0].constraints[0], DVec2::new(-0.5, 1.));
+        assert_close(segments[0].constraints[1], DVec2::new(0.5, -1.));
+    }
+
+    #[test]
+    fn percentages_refer_to_the_view_box() {
+        let path = temp_svg(
+            "percentages",
+            "viewBox=\"0 0 100 50\"",
+            "<rect x=\"10%\" y=\"10%\" width=\"50%\" height=\"50%\"/>",
+        );
+        let segments = Constraints::from_svg(&path).unwrap().constraint_segments;
+
+        assert_eq!(
+            points(&segments[0]),
+            vec![(10., -5.), (60., -5.), (60., -30.), (10., -30.)]
+        );
+    }
+
+    #[test]
+    fn hidden_elements_and_definitions_are_skipped() {
+        let path = temp_svg(
+            "hidden",
+            "",
+            "<defs><path d=\"M0 0 L1 1\"/></defs>\
+             <g display=\"none\"><path d=\"M0 0 L2 2\"/></g>\
+             <path style=\"fill: red; display: none\" d=\"M0 0 L3 3\"/>\
+             <g transform=\"translate(5 0)\"><path id=\"shown\" d=\"M0 0 L1 0\"/></g>",
+        );
+        let segments = Constraints::from_svg(&path).unwrap().constraint_segments;
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].name.as_deref(), Some("shown"));
+        assert_eq!(points(&segments[0]), vec![(5., 0.), (6., 0.)]);
+    }
+
+    #[test]
+    fn invalid_attributes_report_their_line() {
+        let cases = [
+            ("path", "", "<g>\n<path d=\"M0 0 L1 x\"/>\n</g>", 3),
+            ("rect", "", "<rect width=\"ten\" height=\"1\"/>", 2),
+            ("view_box", "viewBox=\"0 0 1\"", "", 1),
+        ];
+
+        for (name, attributes, content, expected_line) in cases {
+            let path = temp_svg(&format!("invalid_{}", name), attributes, content);
+
+            match Constraints::from_svg(&path) {
+                Err(CadetError::Parse { line, .. }) => assert_eq!(line, expected_line, "{}", name),
+                Err(error) => panic!("{} fails with {}", name, error),
+                Ok(_) => panic!("{} is parsed", name),
+            }
+        }
+    }
+}
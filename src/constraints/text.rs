@@ -0,0 +1,308 @@
+use glam::DVec2;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::{
+    cdt::regions::winding_number,
+    error::{CadetError, CadetResult},
+    helper::flatten_bezier,
+};
+
+use super::constraints::ConstraintSegment;
+
+/// Largest distance between a curve of a glyph and the segments replacing
+/// it, as a fraction of the text size
+const TOLERANCE: f64 = 1e-3;
+
+impl ConstraintSegment {
+    /// Outlines `text` set in the font, the data of a TrueType or OpenType
+    /// file, into closed constraint loops numbered from zero in the order of
+    /// the glyphs.
+    ///
+    /// `size` is the height of an em in model units and `origin` is the start
+    /// of the baseline of the first line; further lines go below it. Curves
+    /// are flattened until they deviate less than a thousandth of the size
+    /// from the outline. Outer contours wind counter-clockwise and holes
+    /// clockwise, whatever the convention of the font, so the loops fill the
+    /// glyphs with either fill rule.
+    pub fn from_text(
+        font: &[u8],
+        text: &str,
+        size: f64,
+        origin: DVec2,
+    ) -> CadetResult<Vec<ConstraintSegment>> {
+        let face = Face::parse(font, 0).map_err(|err| CadetError::Font(err.to_string()))?;
+
+        let scale = size / face.units_per_em() as f64;
+        let line_height =
+            (face.ascender() as f64 - face.descender() as f64 + face.line_gap() as f64) * scale;
+
+        let mut loops = Vec::new();
+        let mut pen = origin;
+        let mut previous = None;
+
+        for character in text.chars() {
+            if character == '\n' {
+                pen = DVec2::new(origin.x, pen.y - line_height);
+                previous = None;
+                continue;
+            }
+
+            if character.is_control() {
+                continue;
+            }
+
+            // Characters missing from the font are drawn with its first glyph
+            let glyph = face.glyph_index(character).unwrap_or(GlyphId(0));
+
+            if let Some(previous) = previous {
+                pen.x += kerning(&face, previous, glyph) * scale;
+            }
+
+            let mut outline = GlyphOutline {
+                origin: pen,
+                scale,
+                tolerance: TOLERANCE * size,
+                contours: Vec::new(),
+                contour: Vec::new(),
+            };
+
+            face.outline_glyph(glyph, &mut outline);
+            outline.close();
+
+            loops.extend(orient_contours(outline.contours));
+
+            pen.x += face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale;
+            previous = Some(glyph);
+        }
+
+        Ok(loops
+            .into_iter()
+            .enumerate()
+            .map(|(id, constraints)| ConstraintSegment {
+                constraints,
+                id,
+                closed: true,
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// Horizontal kerning between two glyphs in font units, from the `kern`
+/// table if the font has one
+fn kerning(face: &Face, left: GlyphId, right: GlyphId) -> f64 {
+    face.tables()
+        .kern
+        .and_then(|table| {
+            table
+                .subtables
+                .into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .find_map(|subtable| subtable.glyphs_kerning(left, right))
+        })
+        .unwrap_or(0) as f64
+}
+
+/// Collects the flattened contours of a glyph placed at `origin`
+struct GlyphOutline {
+    origin: DVec2,
+    scale: f64,
+    tolerance: f64,
+    contours: Vec<Vec<DVec2>>,
+    contour: Vec<DVec2>,
+}
+
+impl GlyphOutline {
+    fn point(&self, x: f32, y: f32) -> DVec2 {
+        self.origin + DVec2::new(x as f64, y as f64) * self.scale
+    }
+
+    fn push(&mut self, point: DVec2) {
+        if self.contour.last() != Some(&point) {
+            self.contour.push(point);
+        }
+    }
+
+    /// Adds the points of the Bézier curve from the current point through the
+    /// control points
+    fn flatten(&mut self, controls: &[DVec2]) {
+        let Some(&start) = self.contour.last() else {
+            return;
+        };
+
+        let points = std::iter::once(start)
+            .chain(controls.iter().copied())
+            .collect::<Vec<_>>();
+
+        for point in flatten_bezier(&points, self.tolerance) {
+            self.push(point);
+        }
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        let point = self.point(x, y);
+        self.push(point);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.push(point);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.flatten(&[self.point(x1, y1), self.point(x, y)]);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.flatten(&[self.point(x1, y1), self.point(x2, y2), self.point(x, y)]);
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.contour);
+
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+
+        // Contours without an area do not bound anything
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+/// Winds the contours counter-clockwise, or clockwise if they are nested in
+/// an odd number of other contours
+fn orient_contours(contours: Vec<Vec<DVec2>>) -> Vec<Vec<DVec2>> {
+    let depths = contours
+        .iter()
+        .enumerate()
+        .map(|(i, contour)| {
+            contours
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| j != i && winding_number(other, contour[0]) != 0)
+                .count()
+        })
+        .collect::<Vec<_>>();
+
+    contours
+        .into_iter()
+        .zip(depths)
+        .map(|(mut contour, depth)| {
+            let is_counter_clockwise = signed_area(&contour) > 0.;
+
+            if is_counter_clockwise != (depth % 2 == 0) {
+                contour.reverse();
+            }
+
+            contour
+        })
+        .collect()
+}
+
+/// Shoelace area of the closed polygon, positive if it is counter-clockwise
+fn signed_area(polygon: &[DVec2]) -> f64 {
+    let n = polygon.len();
+
+    (0..n)
+        .map(|i| polygon[i].perp_dot(polygon[(i + 1) % n]))
+        .sum::<f64>()
+        / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use crate::{cdt::regions::winding_number, error::CadetError};
+
+    use super::{signed_area, ConstraintSegment};
+
+    fn outline(text: &str, size: f64, origin: DVec2) -> Vec<ConstraintSegment> {
+        let font = std::fs::read("assets/Roboto-Regular.ttf").unwrap();
+        ConstraintSegment::from_text(&font, text, size, origin).unwrap()
+    }
+
+    fn bounds(segment: &ConstraintSegment) -> (DVec2, DVec2) {
+        segment.constraints.iter().fold(
+            (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        )
+    }
+
+    #[test]
+    fn o_is_an_outer_loop_around_a_hole() {
+        let segments = outline("O", 1., DVec2::ZERO);
+
+        assert_eq!(segments.len(), 2);
+        assert!(segments.iter().all(|segment| segment.closed));
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.id)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+
+        let (outer, hole) = if signed_area(&segments[0].constraints) > 0. {
+            (&segments[0], &segments[1])
+        } else {
+            (&segments[1], &segments[0])
+        };
+
+        assert!(signed_area(&outer.constraints) > 0.);
+        assert!(signed_area(&hole.constraints) < 0.);
+        assert!(hole
+            .constraints
+            .iter()
+            .all(|&p| winding_number(&outer.constraints, p) == 1));
+    }
+
+    #[test]
+    fn glyphs_follow_the_baseline_from_the_origin() {
+        let origin = DVec2::new(3., -2.);
+        let segments = outline("II", 2., origin);
+
+        assert_eq!(segments.len(), 2);
+
+        let (first_min, first_max) = bounds(&segments[0]);
+        let (second_min, _) = bounds(&segments[1]);
+
+        // Roboto's I stands on the baseline and is 0.71 em tall
+        assert!((first_min.y - origin.y).abs() < 1e-9);
+        assert!((second_min.y - origin.y).abs() < 1e-9);
+        assert!(first_min.x > origin.x);
+        assert!(second_min.x > first_max.x);
+        assert!((first_max.y - origin.y - 1.42).abs() < 0.01);
+    }
+
+    #[test]
+    fn lines_go_below_the_first_one() {
+        let segments = outline("I\nI", 1., DVec2::ZERO);
+
+        assert_eq!(segments.len(), 2);
+
+        let (first_min, _) = bounds(&segments[0]);
+        let (second_min, second_max) = bounds(&segments[1]);
+
+        assert!(second_max.y < 0.);
+        assert!((second_min.x - first_min.x).abs() < 1e-9);
+    }
+
+    #[test]
+    fn whitespace_has_no_outline() {
+        assert!(outline(" \t\n ", 1., DVec2::ZERO).is_empty());
+    }
+
+    #[test]
+    fn invalid_fonts_are_rejected() {
+        assert!(matches!(
+            ConstraintSegment::from_text(b"not a font", "O", 1., DVec2::ZERO),
+            Err(CadetError::Font(_))
+        ));
+    }
+}
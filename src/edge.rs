@@ -1,17 +1,22 @@
-use std::{cell::RefCell, collections::HashSet, rc::Rc};
+use std::collections::HashSet;
 
-use crate::vertex::Vertex;
+use crate::{arena::handle, vertex::VertexId};
 
-#[derive(Debug)]
+handle!(
+    /// Handle of a [`Edge`]
+    EdgeId
+);
+
+#[derive(Clone, Debug)]
 pub struct Edge {
-    pub a: Rc<RefCell<Vertex>>,
-    pub b: Rc<RefCell<Vertex>>,
+    pub a: VertexId,
+    pub b: VertexId,
     pub crep: HashSet<usize>, // Constraints represented by this edge
 }
 
 impl Edge {
-    pub fn edge_indices(&self) -> (usize, usize) {
-        (self.a.borrow().index, self.b.borrow().index)
+    pub fn edge_indices(&self) -> (VertexId, VertexId) {
+        (self.a, self.b)
     }
 
     pub fn insert_constraint(&mut self, constraint: usize) {
@@ -28,9 +33,7 @@ impl std::fmt::Display for Edge {
         write!(
             f,
             "Edge {{ a: {}, b: {}, crep: {:?} }}",
-            self.a.borrow().index,
-            self.b.borrow().index,
-            self.crep
+            self.a, self.b, self.crep
         )
     }
 }
@@ -0,0 +1,110 @@
+use glam::DVec2;
+
+use crate::vertex::VertexId;
+
+/// Errors returned by the public entry points of the library
+#[derive(Debug)]
+pub enum CadetError {
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+    /// A line of a constraint file could not be parsed
+    Parse { line: usize, message: String },
+    /// A glTF model could not be loaded or written
+    Gltf(Box<dyn std::error::Error + Send + Sync>),
+    /// An OBJ model could not be parsed
+    Obj(obj::ObjError),
+    /// A font could not be parsed
+    Font(String),
+    /// The model does not contain the requested data
+    EmptyModel,
+    /// The points are all collinear or coincide, so they do not span a face
+    DegeneratePoints,
+    /// A constraint does not contain any point
+    EmptyConstraint(usize),
+    /// A constraint point lies outside of the triangulated domain
+    OutsideDomain { constraint: usize, point: DVec2 },
+    /// No constraint with the given id was inserted
+    UnknownConstraint(usize),
+    /// A point lies outside of the triangulated domain
+    PointOutsideDomain(DVec2),
+    /// Another vertex already occupies the position
+    PositionOccupied { vertex: VertexId, point: DVec2 },
+    /// The vertex is not part of the triangulation
+    UnknownVertex(VertexId),
+    /// The vertex cannot be removed without changing the constraints or the
+    /// shape of the domain
+    UnremovableVertex { vertex: VertexId, reason: String },
+    /// An area bound for refinement is zero, negative or not a number
+    InvalidAreaBound(f64),
+    /// The axes of a projection plane are zero, parallel or not finite
+    DegenerateProjection,
+    /// The SymEdge structure is in an inconsistent state
+    TopologyCorrupt(String),
+}
+
+pub type CadetResult<T> = Result<T, CadetError>;
+
+impl std::fmt::Display for CadetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CadetError::Io(err) => write!(f, "I/O error: {}", err),
+            CadetError::Parse { line, message } => {
+                write!(f, "Parse error on line {}: {}", line, message)
+            }
+            CadetError::Gltf(err) => write!(f, "glTF error: {}", err),
+            CadetError::Obj(err) => write!(f, "Failed to load OBJ model: {}", err),
+            CadetError::Font(message) => write!(f, "Failed to load font: {}", message),
+            CadetError::EmptyModel => write!(f, "The model does not contain any mesh"),
+            CadetError::DegeneratePoints => {
+                write!(f, "The points are collinear and do not span a face")
+            }
+            CadetError::EmptyConstraint(id) => write!(f, "Constraint {} has no points", id),
+            CadetError::OutsideDomain { constraint, point } => write!(
+                f,
+                "Point ({}, {}) of constraint {} is outside of the triangulation",
+                point.x, point.y, constraint
+            ),
+            CadetError::UnknownConstraint(id) => write!(f, "Constraint {} is not inserted", id),
+            CadetError::PointOutsideDomain(point) => write!(
+                f,
+                "Point ({}, {}) is outside of the triangulation",
+                point.x, point.y
+            ),
+            CadetError::PositionOccupied { vertex, point } => write!(
+                f,
+                "Point ({}, {}) is already occupied by vertex {}",
+                point.x, point.y, vertex
+            ),
+            CadetError::UnknownVertex(vertex) => {
+                write!(f, "Vertex {} is not in the triangulation", vertex)
+            }
+            CadetError::UnremovableVertex { vertex, reason } => {
+                write!(f, "Vertex {} cannot be removed, {}", vertex, reason)
+            }
+            CadetError::InvalidAreaBound(area) => {
+                write!(f, "Area bound {} is not a positive number", area)
+            }
+            CadetError::DegenerateProjection => {
+                write!(f, "The projection plane axes do not span a plane")
+            }
+            CadetError::TopologyCorrupt(message) => write!(f, "Corrupt topology: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CadetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CadetError::Io(err) => Some(err),
+            CadetError::Gltf(err) => Some(err.as_ref()),
+            CadetError::Obj(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CadetError {
+    fn from(err: std::io::Error) -> Self {
+        CadetError::Io(err)
+    }
+}
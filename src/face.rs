@@ -1,66 +1,42 @@
-use std::{cell::RefCell, rc::Rc};
+use crate::{arena::handle, edge::Edge, vertex::VertexId};
 
-use crate::{edge::Edge, vertex::Vertex};
+handle!(
+    /// Handle of a [`Face`]
+    FaceId
+);
 
 #[derive(Clone, Debug)]
 pub struct Face {
-    pub id: usize,
-    pub vertices: [Rc<RefCell<Vertex>>; 3],
+    pub vertices: [VertexId; 3],
+    /// Index of the region found by the last [`CDT::classify_regions`],
+    /// faces created since then have none
+    ///
+    /// [`CDT::classify_regions`]: crate::CDT::classify_regions
+    pub region: Option<usize>,
 }
 
 impl Face {
-    pub fn edges(&self) -> [(Rc<RefCell<Vertex>>, Rc<RefCell<Vertex>>); 3] {
+    pub fn edge_indices(&self) -> [(VertexId, VertexId); 3] {
         [
-            (self.vertices[0].clone(), self.vertices[1].clone()),
-            (self.vertices[1].clone(), self.vertices[2].clone()),
-            (self.vertices[2].clone(), self.vertices[0].clone()),
-        ]
-    }
-    pub fn edge_indices(&self) -> [(usize, usize); 3] {
-        [
-            (
-                self.vertices[0].borrow().index,
-                self.vertices[1].borrow().index,
-            ),
-            (
-                self.vertices[1].borrow().index,
-                self.vertices[2].borrow().index,
-            ),
-            (
-                self.vertices[2].borrow().index,
-                self.vertices[0].borrow().index,
-            ),
+            (self.vertices[0], self.vertices[1]),
+            (self.vertices[1], self.vertices[2]),
+            (self.vertices[2], self.vertices[0]),
         ]
     }
 
-    pub fn vertex_indices(&self) -> [usize; 3] {
-        [
-            self.vertices[0].borrow().index,
-            self.vertices[1].borrow().index,
-            self.vertices[2].borrow().index,
-        ]
+    pub fn vertex_indices(&self) -> [VertexId; 3] {
+        self.vertices
     }
 
-    pub fn opposite_vertex(&self, edge: &Edge) -> Rc<RefCell<Vertex>> {
-        let edge = edge.edge_indices();
+    pub fn contains_vertex(&self, vertex: VertexId) -> bool {
+        self.vertices.contains(&vertex)
+    }
 
-        self.vertices
+    pub fn opposite_vertex(&self, edge: &Edge) -> VertexId {
+        *self
+            .vertices
             .iter()
-            .find(|vertex| {
-                let index = vertex.borrow().index;
-                index != edge.0 && index != edge.1
-            })
-            .cloned()
+            .find(|&&vertex| vertex != edge.a && vertex != edge.b)
             .expect("Edge not found in face")
     }
 }
-
-pub trait ToIndices<T> {
-    fn to_indices(&self) -> T;
-}
-
-impl ToIndices<(usize, usize)> for (Rc<RefCell<Vertex>>, Rc<RefCell<Vertex>>) {
-    fn to_indices(&self) -> (usize, usize) {
-        (self.0.borrow().index, self.1.borrow().index)
-    }
-}
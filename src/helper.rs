@@ -1,106 +1,105 @@
 use glam::DVec2;
-use intersection_detection::{Intersection, Line};
 
-use crate::orientation::Orientation;
+use crate::{
+    orientation::Orientation,
+    predicates::{orient2d, orientation, segments_cross},
+};
 
+/// Whether the edges cross in a point that is not an end point of either edge
 pub fn is_crossing(e1: &(DVec2, DVec2), e2: &(DVec2, DVec2)) -> bool {
-    match intersection_point(e1, e2) {
-        Some(p) => {
-            // Check if the intersection point is on the start or end of the edges
-            // With a small epsilon
-            let epsilon = 0.0001;
-            let is_on_edge = |e: &(DVec2, DVec2), p: DVec2| {
-                let (a, b) = e;
-                let ap = p - a;
-                let bp = p - b;
-
-                ap.length() > epsilon && bp.length() > epsilon
-            };
-
-            is_on_edge(e1, p) && is_on_edge(e2, p)
-        }
-        _ => false,
-    }
+    segments_cross(e1.0, e1.1, e2.0, e2.1)
 }
 
+/// Point where the edges intersect, `None` if they are disjoint or parallel.
+///
+/// The sides of the end points are exact, so the result is always found for
+/// edges that [`is_crossing`] reports, but the point itself is rounded.
 pub fn intersection_point(e1: &(DVec2, DVec2), e2: &(DVec2, DVec2)) -> Option<DVec2> {
-    let (a, b) = e1;
-    let (c, d) = e2;
+    let (a, b) = *e1;
+    let (c, d) = *e2;
 
-    let line1 = Line::new([a.x, a.y], [b.x, b.y]);
-    let line2 = Line::new([c.x, c.y], [d.x, d.y]);
+    let side_a = orient2d(c, d, a);
+    let side_b = orient2d(c, d, b);
+    let side_c = orient2d(a, b, c);
+    let side_d = orient2d(a, b, d);
 
-    let computation = line1.intersection(&line2).try_into_intersection().ok();
+    let is_disjoint = (side_a > 0. && side_b > 0.)
+        || (side_a < 0. && side_b < 0.)
+        || (side_c > 0. && side_d > 0.)
+        || (side_c < 0. && side_d < 0.);
 
-    match computation {
-        Some(Intersection::Point(point)) => Some(DVec2::new(point[0], point[1])),
-        _ => None,
+    if is_disjoint || side_a == side_b {
+        return None;
     }
-}
 
-enum IntersectionType {
-    None,
-    Collinear,
-    Parallel,
-    Point(DVec2),
-}
+    let t = side_a / (side_a - side_b);
 
-fn cross_product(a: DVec2, b: DVec2) -> f64 {
-    a.x * b.y - a.y * b.x
+    Some(a + (b - a) * t)
 }
 
-pub fn is_point_in_triangle(a: &DVec2, b: &DVec2, c: &DVec2, p: &DVec2) -> FaceLocateResult {
-    // Compute vectors
-    let ab = b - a;
-    let bc = c - b;
-    let ca = a - c;
-
-    let ap = p - a;
-    let bp = p - b;
-    let cp = p - c;
-
-    // Cross products
-    let cross1 = cross_product(ab, ap);
-    let cross2 = cross_product(bc, bp);
-    let cross3 = cross_product(ca, cp);
-
-    let epsilon = 0.0001;
-
-    if cross1 > epsilon && cross2 > epsilon && cross3 > epsilon {
-        FaceLocateResult::Face
-    } else if cross1.abs() < epsilon && cross2.abs() < epsilon && cross3.abs() < epsilon {
-        FaceLocateResult::Vertex
-    } else if cross1.abs() < epsilon || cross2.abs() < epsilon || cross3.abs() < epsilon {
-        FaceLocateResult::Edge
-    } else {
-        FaceLocateResult::None
+/// Upper bound on the number of segments a single curve is flattened into
+const MAX_CURVE_SEGMENTS: f64 = 64.;
+
+/// Points along the Bézier curve with the given control points, after the
+/// first one and up to the last one, so the chords between them deviate less
+/// than `tolerance` from the curve.
+///
+/// The number of chords follows Wang's formula, which bounds that distance
+/// from the second differences of the control points. Fewer than two
+/// control points give no points.
+pub fn flatten_bezier(points: &[DVec2], tolerance: f64) -> Vec<DVec2> {
+    if points.len() < 2 {
+        return Vec::new();
     }
+
+    let degree = (points.len() - 1) as f64;
+
+    let max_second_difference = points
+        .windows(3)
+        .map(|w| (w[0] - 2. * w[1] + w[2]).length())
+        .fold(0., f64::max);
+
+    let segments = (degree * (degree - 1.) * max_second_difference / (8. * tolerance))
+        .sqrt()
+        .ceil();
+    let segments = if segments.is_finite() {
+        segments.clamp(1., MAX_CURVE_SEGMENTS) as usize
+    } else {
+        MAX_CURVE_SEGMENTS as usize
+    };
+
+    (1..=segments)
+        .map(|i| bezier_point(points, i as f64 / segments as f64))
+        .collect()
 }
 
-pub enum FaceLocateResult {
-    Face,
-    Edge,
-    Vertex,
-    None,
+/// Point of the Bézier curve at `t`, by de Casteljau's algorithm
+fn bezier_point(points: &[DVec2], t: f64) -> DVec2 {
+    let mut points = points.to_vec();
+
+    while points.len() > 1 {
+        points = points.windows(2).map(|w| w[0].lerp(w[1], t)).collect();
+    }
+
+    points[0]
 }
 
-pub fn ccw(a: &DVec2, b: &DVec2, c: &DVec2) -> f64 {
-    let ab = a - b;
-    let ac = a - c;
+/// Center of the circle through the three points
+pub fn circumcenter(a: &DVec2, b: &DVec2, c: &DVec2) -> DVec2 {
+    let ab = b - a;
+    let ac = c - a;
+    let d = 2. * ab.perp_dot(ac);
+
+    let center = DVec2::new(
+        ac.y * ab.length_squared() - ab.y * ac.length_squared(),
+        ab.x * ac.length_squared() - ac.x * ab.length_squared(),
+    ) / d;
 
-    ab.x * ac.y - ab.y * ac.x
+    a + center
 }
 
 pub fn is_ccw(a: &DVec2, b: &DVec2, c: &DVec2) -> Orientation {
-    let ccw = ccw(a, b, c);
-    let distance = ccw.abs() / ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
-    if distance < 1e-6 {
-        return Orientation::Collinear;
-    }
-    if ccw > 0.0 {
-        return Orientation::CounterClockwise;
-    }
-    Orientation::Clockwise
+    orientation(*a, *b, *c)
 }
 
 pub trait ProjectToLine {
@@ -118,3 +117,27 @@ impl ProjectToLine for DVec2 {
         a + ab * t
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::flatten_bezier;
+
+    #[test]
+    fn flattening_without_a_curve_gives_no_points() {
+        assert!(flatten_bezier(&[], 0.1).is_empty());
+        assert!(flatten_bezier(&[DVec2::ONE], 0.1).is_empty());
+    }
+
+    #[test]
+    fn flattened_curves_end_at_the_last_control_point() {
+        let line = flatten_bezier(&[DVec2::ZERO, DVec2::X], 0.1);
+        assert_eq!(line, vec![DVec2::X]);
+
+        let points = [DVec2::ZERO, DVec2::new(1., 2.), DVec2::new(2., 0.)];
+        let curve = flatten_bezier(&points, 0.01);
+        assert!(curve.len() > 1);
+        assert!(curve.last().unwrap().distance(points[2]) < 1e-12);
+    }
+}
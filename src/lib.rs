@@ -0,0 +1,38 @@
+//! CADET: Constrained Delaunay Triangulation.
+//!
+//! The library exposes the [`CDT`] mesh together with the constraint types used
+//! to drive it. The modules are internal; the mesh building blocks ([`Vertex`],
+//! [`Edge`], [`Face`], [`SymEdge`]) are re-exported so callers can inspect the
+//! triangulation after constraints have been inserted.
+
+mod arena;
+mod cdt;
+mod constraints;
+mod edge;
+mod error;
+mod face;
+mod helper;
+mod locate_result;
+mod orientation;
+mod predicates;
+mod projection;
+mod sym_edge;
+mod symmetric_compare;
+mod vertex;
+
+pub use arena::{Arena, Handle};
+pub use cdt::cdt::{ConstraintIntersection, CDT};
+pub use cdt::io::ImportOptions;
+pub use cdt::refinement::{AreaSeed, RefinementOptions, RefinementReport, SizingFunction};
+pub use cdt::regions::{FillRule, Region, RegionOptions};
+pub use cdt::svg::SvgExportOptions;
+pub use cdt::validation::{ValidationIssue, ValidationReport};
+pub use constraints::constraints::{ConstraintSegment, Constraints, SegmentTag};
+pub use constraints::svg::SvgOptions;
+pub use edge::{Edge, EdgeId};
+pub use error::{CadetError, CadetResult};
+pub use face::{Face, FaceId};
+pub use locate_result::LocateResult;
+pub use projection::Projection;
+pub use sym_edge::{SymEdge, SymEdgeId};
+pub use vertex::{Vertex, VertexId};
@@ -1,11 +1,9 @@
-use std::{cell::RefCell, rc::Rc};
+use crate::{edge::EdgeId, face::FaceId, vertex::VertexId};
 
-use crate::{edge::Edge, face::Face, vertex::Vertex};
-
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LocateResult {
-    Vertex(Rc<RefCell<Vertex>>),
-    Edge(Rc<RefCell<Edge>>),
-    Face(Rc<RefCell<Face>>),
+    Vertex(VertexId),
+    Edge(EdgeId),
+    Face(FaceId),
     None,
 }
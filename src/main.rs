@@ -1,19 +1,6 @@
-use cdt::cdt::CDT;
-use constraints::constraints::{ConstraintSegment, Constraints};
-use glam::DVec2;
+use cadet::{CadetError, Constraints, SvgExportOptions, CDT};
 use std::io::{self, BufRead};
 
-pub mod cdt;
-pub mod constraints;
-pub mod edge;
-pub mod face;
-pub mod helper;
-pub mod locate_result;
-pub mod orientation;
-pub mod sym_edge;
-pub mod symmetric_compare;
-pub mod vertex;
-
 fn get_path_from_stdin(prompt: &str) -> String {
     println!("{}", prompt);
     let mut input = String::new();
@@ -27,6 +14,13 @@ fn get_path_from_stdin(prompt: &str) -> String {
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
 
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), CadetError> {
     let args: Vec<String> = std::env::args().collect();
 
     let input_path = if args.len() > 1 {
@@ -47,43 +41,51 @@ fn main() {
         get_path_from_stdin("Enter output path:")
     };
 
-    let constraints = Constraints {
-        constraint_segments: vec![ConstraintSegment::generate_square(
-            DVec2 { x: 0., y: 0. },
-            0.75 * 2.,
-            0,
-        )],
+    let mut cdt = if input_path.ends_with(".obj") {
+        CDT::from_obj(&input_path)?
+    } else {
+        CDT::from_gltf(&input_path)?
     };
+    cdt.build_sym_edges()?;
 
-    // constraints.export("./constraints/square_0-75.ct");
-
-    // return;
-
-    let constraints = Constraints::load(&constraint_path);
-
-    let mut cdt = CDT::from_gltf(&input_path);
-    cdt.build_sym_edges().unwrap();
+    // OBJ files can hold the constraints as line elements
+    let constraints = if constraint_path.ends_with(".obj") {
+        Constraints::from_obj(&constraint_path, cdt.projection)?
+    } else {
+        Constraints::load(&constraint_path)?
+    };
 
     // cdt.export_to_obj("./models/output.obj");
 
     // // Wait 100ms
     // std::thread::sleep(std::time::Duration::from_millis(1000));
 
-    cdt.add_constraints(&constraints);
+    let intersections = cdt.add_constraints(&constraints)?;
 
     println!("Number of faces: {}", cdt.faces.len());
-
-    //Verify that for every sym_edge, the face exists
-    for sym_edge in cdt.sym_edges_by_half_edges.values() {
-        let sym_edge = sym_edge.borrow();
-
-        let is_any_face = cdt
-            .faces
-            .iter()
-            .any(|face| face.as_ptr() == sym_edge.face.as_ptr());
-
-        assert!(is_any_face);
+    println!(
+        "Number of constraint intersections: {}",
+        intersections.len()
+    );
+
+    let report = cdt.validate();
+    if !report.is_valid() {
+        return Err(CadetError::TopologyCorrupt(report.to_string()));
+    }
+    println!(
+        "Number of non-Delaunay edges: {}",
+        report.non_delaunay_edges.len()
+    );
+
+    let is_gltf = [".glb", ".gltf"]
+        .iter()
+        .any(|extension| output_path.ends_with(extension));
+
+    if is_gltf {
+        cdt.export_to_gltf(&output_path)
+    } else if output_path.ends_with(".svg") {
+        cdt.export_to_svg(&output_path, &SvgExportOptions::default())
+    } else {
+        cdt.export_to_obj(&output_path)
     }
-
-    cdt.export_to_obj(&output_path);
 }
@@ -0,0 +1,183 @@
+//! Exact geometric predicates.
+//!
+//! The signs of the orientation and in-circle determinants are evaluated with
+//! Shewchuk's adaptive-precision arithmetic, so the results are exact for any
+//! `f64` input and do not depend on the scale of the model.
+
+use std::cmp::Ordering;
+
+use glam::DVec2;
+use robust::Coord;
+
+use crate::orientation::Orientation;
+
+fn coord(p: DVec2) -> Coord<f64> {
+    Coord { x: p.x, y: p.y }
+}
+
+/// Positive if `a`, `b`, `c` are in counter-clockwise order, negative if they
+/// are clockwise and zero if they are collinear.
+///
+/// Only the sign of the result is exact, the magnitude is an approximation of
+/// twice the signed area of the triangle.
+pub fn orient2d(a: DVec2, b: DVec2, c: DVec2) -> f64 {
+    robust::orient2d(coord(a), coord(b), coord(c))
+}
+
+pub fn orientation(a: DVec2, b: DVec2, c: DVec2) -> Orientation {
+    let det = orient2d(a, b, c);
+
+    if det > 0. {
+        Orientation::CounterClockwise
+    } else if det < 0. {
+        Orientation::Clockwise
+    } else {
+        Orientation::Collinear
+    }
+}
+
+/// Compares the directions from `center` to `a` and to `b` by their angle in
+/// `(-π, π]`, like `to_angle` does but without rounding, so directions that
+/// differ by less than its precision are still ordered.
+pub fn compare_angles(center: DVec2, a: DVec2, b: DVec2) -> Ordering {
+    // Directions along the negative x axis have the angle π
+    let is_upper = |p: DVec2| p.y > center.y || (p.y == center.y && p.x < center.x);
+
+    match (is_upper(a), is_upper(b)) {
+        (false, true) => Ordering::Less,
+        (true, false) => Ordering::Greater,
+        _ => 0.0.partial_cmp(&orient2d(center, a, b)).unwrap(),
+    }
+}
+
+/// Positive if `d` lies inside the circle through the counter-clockwise
+/// triangle `a`, `b`, `c`, negative if outside and zero if the points are
+/// cocircular. The sign is reversed for clockwise triangles.
+pub fn incircle(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> f64 {
+    robust::incircle(coord(a), coord(b), coord(c), coord(d))
+}
+
+/// Whether `d` lies strictly inside the circumcircle of the triangle, for
+/// either winding. Degenerate triangles have no interior.
+pub fn in_circumcircle(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> bool {
+    let winding = orient2d(a, b, c);
+
+    if winding == 0. {
+        return false;
+    }
+
+    incircle(a, b, c, d) * winding.signum() > 0.
+}
+
+/// Whether `p` lies inside or on the boundary of the triangle, for either
+/// winding.
+pub fn in_triangle(a: DVec2, b: DVec2, c: DVec2, p: DVec2) -> bool {
+    let sides = [orient2d(a, b, p), orient2d(b, c, p), orient2d(c, a, p)];
+
+    sides.iter().all(|&side| side >= 0.) || sides.iter().all(|&side| side <= 0.)
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` cross in a single point that is
+/// not an end point of either segment.
+pub fn segments_cross(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> bool {
+    let separates = |p: DVec2, q: DVec2, r: DVec2, s: DVec2| {
+        let side_r = orient2d(p, q, r);
+        let side_s = orient2d(p, q, s);
+
+        (side_r > 0. && side_s < 0.) || (side_r < 0. && side_s > 0.)
+    };
+
+    separates(a, b, c, d) && separates(c, d, a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn orientation_of_nearly_collinear_points_is_exact() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            let mut xs = [0.; 3].map(|_| rng.gen_range(-1e6..1e6));
+            xs.sort_by(f64::total_cmp);
+            let [a, b, c] = xs.map(|x| DVec2::new(x, x));
+
+            // Points on y = x are collinear, and one ulp off it is not
+            assert_eq!(orientation(a, b, c), Orientation::Collinear);
+
+            let above = DVec2::new(c.x, c.y.next_up());
+            let below = DVec2::new(c.x, c.y.next_down());
+            assert_eq!(orientation(a, b, above), Orientation::CounterClockwise);
+            assert_eq!(orientation(a, b, below), Orientation::Clockwise);
+        }
+    }
+
+    #[test]
+    fn in_circumcircle_of_nearly_cocircular_points_is_exact() {
+        let [a, b, c] = [DVec2::X, DVec2::Y, DVec2::NEG_X];
+
+        assert_eq!(incircle(a, b, c, DVec2::NEG_Y), 0.);
+        assert!(!in_circumcircle(a, b, c, DVec2::NEG_Y));
+        assert!(in_circumcircle(a, b, c, DVec2::new(0., (-1f64).next_up())));
+        assert!(!in_circumcircle(
+            a,
+            b,
+            c,
+            DVec2::new(0., (-1f64).next_down())
+        ));
+
+        // The winding of the triangle does not matter
+        assert!(in_circumcircle(c, b, a, DVec2::new(0., (-1f64).next_up())));
+
+        // Nor does a degenerate triangle contain anything
+        assert!(!in_circumcircle(a, DVec2::ZERO, c, DVec2::new(0., 0.5)));
+    }
+
+    #[test]
+    fn angles_closer_than_to_angle_precision_are_ordered() {
+        // Nearly vertical directions, b leaning slightly more to the right
+        let center = DVec2::ZERO;
+        let a = DVec2::new(1., 1e16);
+        let b = DVec2::new(1f64.next_up(), 1e16);
+
+        assert_eq!(a.to_angle(), b.to_angle());
+        assert_eq!(compare_angles(center, a, b), Ordering::Greater);
+        assert_eq!(compare_angles(center, b, a), Ordering::Less);
+        assert_eq!(compare_angles(center, a, a * 2.), Ordering::Equal);
+
+        // The negative x axis has the largest angle
+        let negative_x = center - DVec2::X;
+        let just_below = DVec2::new(negative_x.x, negative_x.y.next_down());
+        assert_eq!(
+            compare_angles(center, negative_x, just_below),
+            Ordering::Greater
+        );
+        assert_eq!(compare_angles(center, negative_x, a), Ordering::Greater);
+    }
+
+    #[test]
+    fn touching_segments_do_not_cross() {
+        let a = DVec2::ZERO;
+        let b = DVec2::new(1., 1.);
+
+        assert!(segments_cross(a, b, DVec2::new(0., 1.), DVec2::new(1., 0.)));
+        // Sharing an end point, or ending on the other segment
+        assert!(!segments_cross(a, b, b, DVec2::new(2., 0.)));
+        assert!(!segments_cross(a, b, DVec2::splat(0.5), DVec2::new(1., 0.)));
+        // Collinear overlap
+        assert!(!segments_cross(a, b, DVec2::splat(0.5), DVec2::splat(2.)));
+    }
+
+    #[test]
+    fn triangle_contains_its_boundary() {
+        let [a, b, c] = [DVec2::ZERO, DVec2::X, DVec2::Y];
+
+        assert!(in_triangle(a, b, c, DVec2::new(0.5, 0.5)));
+        assert!(in_triangle(c, b, a, DVec2::new(0.5, 0.5)));
+        assert!(in_triangle(a, b, c, a));
+        assert!(!in_triangle(a, b, c, DVec2::new(0.5, 0.5f64.next_up())));
+    }
+}
@@ -0,0 +1,164 @@
+use glam::{DMat3, DVec2, DVec3};
+
+use crate::error::{CadetError, CadetResult};
+
+/// Plane a 3D model is projected onto before it is triangulated.
+///
+/// The coordinate along the third axis is kept as the height of every
+/// vertex, and exporting applies the inverse transform.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Projection {
+    /// Height along Z, for Z-up assets
+    XY,
+    /// Height along Y, for Y-up assets
+    #[default]
+    XZ,
+    /// Height along X
+    YZ,
+    /// Arbitrary plane through `origin`, spanned by `u` and `v`. The height
+    /// is measured along their normalized cross product, so the axes must be
+    /// neither zero nor parallel.
+    Plane { origin: DVec3, u: DVec3, v: DVec3 },
+}
+
+impl Projection {
+    /// Origin and the matrix whose columns are the two plane axes and the
+    /// height axis
+    fn basis(&self) -> (DVec3, DMat3) {
+        match *self {
+            Projection::XY => (DVec3::ZERO, DMat3::from_cols(DVec3::X, DVec3::Y, DVec3::Z)),
+            Projection::XZ => (DVec3::ZERO, DMat3::from_cols(DVec3::X, DVec3::Z, DVec3::Y)),
+            Projection::YZ => (DVec3::ZERO, DMat3::from_cols(DVec3::Y, DVec3::Z, DVec3::X)),
+            Projection::Plane { origin, u, v } => {
+                (origin, DMat3::from_cols(u, v, u.cross(v).normalize()))
+            }
+        }
+    }
+
+    /// Checks that the axes of a plane span it, so points can be projected
+    /// onto it and back
+    pub(crate) fn check(&self) -> CadetResult<()> {
+        match *self {
+            Projection::Plane { origin, u, v } => {
+                // Relative to the axes, so that short axes are not rejected
+                let normal = u.cross(v);
+                let spans_plane = normal.length() > 1e-12 * u.length() * v.length()
+                    && normal.is_finite()
+                    && origin.is_finite();
+
+                if spans_plane {
+                    Ok(())
+                } else {
+                    Err(CadetError::DegenerateProjection)
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Position in the plane and height of a point
+    pub fn project(&self, p: DVec3) -> (DVec2, f64) {
+        let (origin, basis) = self.basis();
+        let coordinates = basis.inverse() * (p - origin);
+
+        (coordinates.truncate(), coordinates.z)
+    }
+
+    /// Point with the given position in the plane and height
+    pub fn unproject(&self, position: DVec2, height: f64) -> DVec3 {
+        let (origin, basis) = self.basis();
+
+        origin + basis * position.extend(height)
+    }
+
+    /// Whether the plane axes and the height axis form a left-handed basis,
+    /// in which faces wound clockwise in the plane are counter-clockwise
+    /// when seen from the positive height axis
+    pub(crate) fn is_mirrored(&self) -> bool {
+        self.basis().1.determinant() < 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DVec2, DVec3};
+
+    use crate::error::CadetError;
+
+    use super::Projection;
+
+    const POINT: DVec3 = DVec3::new(1., 2., 3.);
+
+    fn assert_round_trip(projection: Projection, p: DVec3) {
+        let (position, height) = projection.project(p);
+        let back = projection.unproject(position, height);
+
+        assert!(back.distance(p) < 1e-12, "{:?} came back as {:?}", p, back);
+    }
+
+    #[test]
+    fn axis_planes_keep_the_third_coordinate_as_height() {
+        let cases = [
+            (Projection::XY, DVec2::new(1., 2.), 3.),
+            (Projection::XZ, DVec2::new(1., 3.), 2.),
+            (Projection::YZ, DVec2::new(2., 3.), 1.),
+        ];
+
+        for (projection, position, height) in cases {
+            assert_eq!(projection.project(POINT), (position, height));
+            assert_eq!(projection.unproject(position, height), POINT);
+        }
+    }
+
+    #[test]
+    fn planes_measure_from_their_origin_along_their_axes() {
+        let projection = Projection::Plane {
+            origin: DVec3::new(1., 1., 1.),
+            u: DVec3::new(2., 0., 0.),
+            v: DVec3::new(1., 1., 0.),
+        };
+
+        let (position, height) = projection.project(DVec3::new(4., 2., -1.));
+        assert!(position.distance(DVec2::new(1., 1.)) < 1e-12);
+        assert!((height + 2.).abs() < 1e-12);
+
+        assert_round_trip(projection, POINT);
+        assert_round_trip(projection, DVec3::new(-5., 0.5, 7.));
+        assert!(projection.check().is_ok());
+    }
+
+    #[test]
+    fn planes_without_two_independent_axes_are_rejected() {
+        let plane = |u: DVec3, v: DVec3| Projection::Plane {
+            origin: DVec3::ZERO,
+            u,
+            v,
+        };
+
+        let degenerate = [
+            plane(DVec3::ZERO, DVec3::Y),
+            plane(DVec3::X, DVec3::ZERO),
+            plane(DVec3::X, DVec3::new(-3., 0., 0.)),
+            plane(DVec3::new(0.1, 0.2, 0.3), DVec3::new(0.3, 0.6, 0.9)),
+            plane(DVec3::X, DVec3::new(f64::NAN, 1., 0.)),
+        ];
+
+        for projection in degenerate {
+            assert!(
+                matches!(projection.check(), Err(CadetError::DegenerateProjection)),
+                "{:?} was accepted",
+                projection
+            );
+        }
+
+        // Short axes still span a plane
+        assert!(plane(1e-9 * DVec3::X, 1e-9 * DVec3::Y).check().is_ok());
+    }
+
+    #[test]
+    fn only_left_handed_bases_are_mirrored() {
+        assert!(!Projection::XY.is_mirrored());
+        assert!(Projection::XZ.is_mirrored());
+        assert!(!Projection::YZ.is_mirrored());
+    }
+}
@@ -1,18 +1,26 @@
 use glam::DVec2;
 
+use crate::arena::handle;
+
+handle!(
+    /// Handle of a [`Vertex`]
+    VertexId
+);
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Vertex {
-    pub index: usize,       // Index of the vertex
     pub position: DVec2,    // Position of the vertex
+    pub height: f64,        // Coordinate dropped when projecting the model to 2D
     pub constraints: usize, // Number of constraints referencing this vertex
+    pub steiner: bool,      // Inserted at a constraint crossing or by refinement
 }
 
 impl std::fmt::Display for Vertex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Vertex {{ index: {}, x: {}, y: {}, constraints: {} }}",
-            self.index, self.position.x, self.position.y, self.constraints
+            "Vertex {{ x: {}, y: {}, height: {}, constraints: {}, steiner: {} }}",
+            self.position.x, self.position.y, self.height, self.constraints, self.steiner
         )
     }
 }